@@ -0,0 +1,613 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Procedural macros of the [DebugOff](https://docs.rs/debugoff) library.
+//!
+//! The macros are re-exported by `debugoff` when its `macros` feature is enabled and are meant to
+//! be used through it (e.g., `#[debugoff::protect]`). The generated code refers to the checks as
+//! `::debugoff::*`, so the `debugoff` crate must not be renamed.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use syn::meta::ParseNestedMeta;
+use syn::parse::Parse;
+use syn::parse::Parser;
+use syn::{Error, ItemFn, Lifetime, LitStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Ptrace,
+    MultiPtrace,
+    TracerPid,
+    Timing,
+    Integrity,
+    Injection,
+    Parent,
+    SigTrap,
+    Guardian,
+    Dumpable,
+}
+
+impl Check {
+    fn from_name(name: &str) -> Option<Check> {
+        match name {
+            "ptrace" => Some(Check::Ptrace),
+            "multi_ptrace" => Some(Check::MultiPtrace),
+            "tracerpid" => Some(Check::TracerPid),
+            "timing" => Some(Check::Timing),
+            "integrity" => Some(Check::Integrity),
+            "injection" => Some(Check::Injection),
+            "parent" => Some(Check::Parent),
+            "sigtrap" => Some(Check::SigTrap),
+            "guardian" => Some(Check::Guardian),
+            "dumpable" => Some(Check::Dumpable),
+            _ => None,
+        }
+    }
+
+    fn call(self) -> TokenStream2 {
+        match self {
+            Check::Ptrace => quote!(::debugoff::ptraceme_or_die()),
+            Check::MultiPtrace => quote!(::debugoff::multi_ptraceme_or_die()),
+            Check::TracerPid => quote!(::debugoff::tracerpid_or_die()),
+            Check::Timing => {
+                quote!(::debugoff::timing::timed_or_die(
+                    ::debugoff::multi_ptraceme_or_die
+                ))
+            }
+            Check::Integrity => quote!(::debugoff::integrity::verify_or_die()),
+            Check::Injection => quote!(::debugoff::injection_or_die()),
+            Check::Parent => quote!(::debugoff::parent_or_die()),
+            Check::SigTrap => quote!(::debugoff::sigtrap_or_die()),
+            Check::Guardian => quote!(::debugoff::guardian::verify_or_die()),
+            Check::Dumpable => quote!(::debugoff::dumpable_or_die()),
+        }
+    }
+
+    /// Same as `call`, with the `ptrace` call chains of `multi_ptrace` and `timing` expanded with
+    /// a shape and constants drawn for the call site.
+    fn call_here(self) -> TokenStream2 {
+        match self {
+            Check::MultiPtrace => multi_ptrace_here(),
+            Check::Timing => {
+                let chain = multi_ptrace_here();
+                quote!(::debugoff::timing::timed_or_die(|| #chain))
+            }
+            _ => self.call(),
+        }
+    }
+}
+
+/// Unrolled chains of calls to `ptrace` with a shape and constants drawn at random: 4 to 16 chains
+/// of 1 to 6 calls. Every call is a separate `ptrace` site, with its own constants.
+fn multi_ptrace_here() -> TokenStream2 {
+    let label = Lifetime::new("'debugoff_multi", Span::mixed_site());
+    let v = format_ident!("v", span = Span::mixed_site());
+    let n = format_ident!("n", span = Span::mixed_site());
+    let offset = format_ident!("offset", span = Span::mixed_site());
+    let e = format_ident!("e", span = Span::mixed_site());
+
+    let outer = 4 + random() % 13;
+    let chains = (0..outer).map(|_| {
+        let len = 1 + (random() % 6) as usize;
+        let salt = random() as u32;
+        let calls = (0..len).map(|_| {
+            quote! {
+                if let ::core::result::Result::Err(#e) =
+                    ::debugoff::__chain_ptraceme(&mut #v, &mut #n, &mut #offset, #salt)
+                {
+                    break #label ::core::result::Result::Err(#e);
+                }
+            }
+        });
+        quote! {{
+            let mut #v = [0u32; #len];
+            let mut #n: usize = 0;
+            let mut #offset: u32 = 0;
+            #(#calls)*
+            if let ::core::result::Result::Err(#e) =
+                ::debugoff::__chain_check(&#v[..#n], #offset, #salt)
+            {
+                break #label ::core::result::Result::Err(#e);
+            }
+        }}
+    });
+
+    quote! {
+        ::debugoff::__multi_ptraceme_react(#label: {
+            #(#chains)*
+            ::core::result::Result::Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Entry,
+    Exit,
+}
+
+#[derive(Debug)]
+struct Args {
+    checks: Vec<Check>,
+    placements: Vec<Placement>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            checks: vec![Check::Ptrace],
+            placements: vec![Placement::Entry],
+        }
+    }
+}
+
+/// Random value which changes at each expansion (and thus at each build).
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Split a comma separated list of names, e.g. `"ptrace, tracerpid"`.
+fn names(lit: &LitStr) -> Result<Vec<String>, Error> {
+    let names: Vec<String> = lit
+        .value()
+        .split(',')
+        .map(|n| n.trim().to_string())
+        .collect();
+    if names.iter().any(|n| n.is_empty()) {
+        return Err(Error::new(lit.span(), "empty name in list"));
+    }
+    Ok(names)
+}
+
+impl Args {
+    fn parse_meta(&mut self, meta: ParseNestedMeta) -> Result<(), Error> {
+        if meta.path.is_ident("checks") {
+            let lit: LitStr = meta.value()?.parse()?;
+            self.checks = names(&lit)?
+                .iter()
+                .map(|n| {
+                    Check::from_name(n)
+                        .ok_or_else(|| Error::new(lit.span(), format!("unknown check `{}`", n)))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(())
+        } else if meta.path.is_ident("placement") {
+            let lit: LitStr = meta.value()?.parse()?;
+            self.placements = names(&lit)?
+                .iter()
+                .map(|n| match n.as_str() {
+                    "entry" => Ok(Placement::Entry),
+                    "exit" => Ok(Placement::Exit),
+                    "random" if random() & 1 == 0 => Ok(Placement::Entry),
+                    "random" => Ok(Placement::Exit),
+                    _ => Err(Error::new(lit.span(), format!("unknown placement `{}`", n))),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(())
+        } else {
+            Err(meta.error("expected `checks` or `placement`"))
+        }
+    }
+}
+
+fn expand(args: Args, item: ItemFn) -> Result<TokenStream2, Error> {
+    if let Some(c) = item.sig.constness {
+        return Err(Error::new_spanned(
+            c,
+            "`protect` cannot be applied to a const fn",
+        ));
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+
+    let gate = quote! {
+        #[cfg(target_os = "linux")]
+        #[cfg(not(debug_assertions))]
+    };
+
+    let calls: Vec<TokenStream2> = args.checks.iter().map(|c| c.call()).collect();
+
+    let entry = if args.placements.contains(&Placement::Entry) {
+        quote! { #( #gate #calls; )* }
+    } else {
+        quote! {}
+    };
+
+    // Checks at exit are run by a guard dropped when the function returns, whatever the return
+    // path is (including early returns and `?`).
+    let exit = if args.placements.contains(&Placement::Exit) {
+        let guard = format_ident!("DebugOffExitGuard", span = Span::mixed_site());
+        let var = format_ident!("debugoff_exit_guard", span = Span::mixed_site());
+        quote! {
+            #gate
+            struct #guard;
+            #gate
+            impl ::core::ops::Drop for #guard {
+                #[inline(always)]
+                fn drop(&mut self) {
+                    #( #calls; )*
+                }
+            }
+            #gate
+            let #var = #guard;
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #entry
+            #exit
+            #block
+        }
+    })
+}
+
+fn expand_here(args: Args) -> TokenStream2 {
+    let gate = quote! {
+        #[cfg(target_os = "linux")]
+        #[cfg(not(debug_assertions))]
+    };
+
+    // Fisher-Yates shuffle of the checks.
+    let mut checks = args.checks;
+    for i in (1..checks.len()).rev() {
+        checks.swap(i, (random() % (i as u64 + 1)) as usize);
+    }
+
+    let calls: Vec<TokenStream2> = checks.iter().map(|c| c.call_here()).collect();
+    quote! { #( #gate #calls; )* }
+}
+
+fn expand_critical(item: ItemFn) -> Result<TokenStream2, Error> {
+    if let Some(c) = item.sig.constness {
+        return Err(Error::new_spanned(
+            c,
+            "`critical` cannot be applied to a const fn",
+        ));
+    }
+    // The body of an async fn is compiled in a different function.
+    if let Some(a) = item.sig.asyncness {
+        return Err(Error::new_spanned(
+            a,
+            "`critical` cannot be applied to an async fn",
+        ));
+    }
+    if let Some(attr) = item
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("inline") || a.path().is_ident("link_section"))
+    {
+        return Err(Error::new_spanned(
+            attr,
+            "`critical` functions cannot have `inline` or `link_section` attributes",
+        ));
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+
+    // The function is placed in a section of its own: the linker defines the `__start_<section>`
+    // and `__stop_<section>` symbols at its bounds.
+    let name = sig.ident.to_string();
+    let section = format!(
+        "debugoff_{}_{:016x}",
+        name.trim_start_matches("r#"),
+        random()
+    );
+    let start_symbol = format!("__start_{}", section);
+    let stop_symbol = format!("__stop_{}", section);
+    let start = format_ident!("DEBUGOFF_START", span = Span::mixed_site());
+    let stop = format_ident!("DEBUGOFF_STOP", span = Span::mixed_site());
+    let begin = format_ident!("begin", span = Span::mixed_site());
+
+    let gate = quote! {
+        #[cfg(target_os = "linux")]
+        #[cfg(not(debug_assertions))]
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #[inline(never)]
+        #[cfg_attr(target_os = "linux", link_section = #section)]
+        #vis #sig {
+            #gate
+            extern "C" {
+                #[link_name = #start_symbol]
+                static #start: u8;
+                #[link_name = #stop_symbol]
+                static #stop: u8;
+            }
+            #gate
+            #[allow(unused_unsafe)]
+            unsafe {
+                let #begin = ::core::ptr::addr_of!(#start);
+                ::debugoff::scan_breakpoints_or_die(
+                    #begin,
+                    ::core::ptr::addr_of!(#stop) as usize - #begin as usize,
+                );
+            }
+            #block
+        }
+    })
+}
+
+/// Insert anti-debugging checks in the annotated function.
+///
+/// The checks are inserted only when building for Linux in release mode (i.e., they are gated by
+/// `#[cfg(target_os = "linux")]` and `#[cfg(not(debug_assertions))]`) and react according to
+/// the configured `ResponsePolicy` (by default, they call `exit_group(0)`).
+///
+/// Arguments:
+///
+/// - `checks`: comma separated list of checks to run, among `ptrace`
+///   (`ptraceme_or_die`), `multi_ptrace` (`multi_ptraceme_or_die`), `tracerpid`
+///   (`tracerpid_or_die`), `timing` (`multi_ptraceme_or_die` in a timed region), `integrity`
+///   (`integrity::verify_or_die`, the binary must be sealed after linking), `injection`
+///   (`injection_or_die`), `parent` (`parent_or_die`), `sigtrap` (`sigtrap_or_die`),
+///   `guardian` (`guardian::verify_or_die`, the guardian must have been started) and `dumpable`
+///   (`dumpable_or_die`, the process must have been hardened). Default: `"ptrace"`.
+/// - `placement`: comma separated list of places where the checks are run, among `entry`
+///   (before the function body), `exit` (when the function returns) and `random` (either entry
+///   or exit, chosen at each build). Default: `"entry"`.
+///
+/// ## Examples
+///
+/// ```rust,ignore
+/// #[debugoff::protect(checks = "ptrace,tracerpid", placement = "entry,random")]
+/// fn main() {
+///     println!("Hello!");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn protect(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = Args::default();
+    let parser = syn::meta::parser(|meta| args.parse_meta(meta));
+
+    let res = parser
+        .parse(attr)
+        .and_then(|_| syn::parse::<ItemFn>(item))
+        .and_then(|item| expand(args, item));
+
+    match res {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Insert anti-debugging checks at the call site, with a code shape drawn for each call site.
+///
+/// The checks are inserted as by `protect` (only for Linux in release mode) but their order is
+/// shuffled at each expansion, and the `ptrace` call chains of `multi_ptrace` and `timing` are
+/// unrolled at the call site, with the number and length of the chains and their constants drawn
+/// for it. Every protected location thus runs different code and cannot be matched by the
+/// signature of another one.
+///
+/// Arguments:
+///
+/// - `checks`: comma separated list of checks to run, as for `protect`. Default:
+///   `"multi_ptrace"`.
+///
+/// The macro expands to statements. As the order of the checks is not known in advance, `sigtrap`
+/// should not be combined with the checks calling `ptrace(PTRACE_TRACEME, ...)` (`ptrace`,
+/// `multi_ptrace` and `timing`): once the parent process is the tracer, it intercepts the
+/// `SIGTRAP`.
+///
+/// ## Examples
+///
+/// ```rust,ignore
+/// fn main() {
+///     debugoff::protect_here!(checks = "multi_ptrace, tracerpid, parent");
+///     println!("Hello!");
+///     debugoff::protect_here!();
+/// }
+/// ```
+#[proc_macro]
+pub fn protect_here(input: TokenStream) -> TokenStream {
+    let mut args = Args {
+        checks: vec![Check::MultiPtrace],
+        ..Args::default()
+    };
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("placement") {
+            return Err(meta.error("`protect_here` does not accept `placement`"));
+        }
+        args.parse_meta(meta)
+    });
+
+    match parser.parse(input) {
+        Ok(()) => expand_here(args).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Scan the annotated function for software breakpoints every time it is entered.
+///
+/// The function is placed in a linker section of its own (whose name is randomized at each
+/// build) and marked `#[inline(never)]`, so its bounds are known at link time. At entry, the whole
+/// function is scanned with `scan_breakpoints_or_die`.
+///
+/// As for `protect`, the scan is inserted only when building for Linux in release mode. Closures
+/// defined in the function are compiled as separate functions and are not scanned.
+///
+/// ## Examples
+///
+/// ```rust,ignore
+/// #[debugoff::critical]
+/// fn check_license(key: &str) -> bool {
+///     key == "secret"
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn critical(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let res = syn::parse::Nothing::parse
+        .parse(attr)
+        .and_then(|_| syn::parse::<ItemFn>(item))
+        .and_then(expand_critical);
+
+    match res {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{expand, expand_critical, expand_here, Args, Check, Placement};
+    use syn::parse::Parser;
+    use syn::parse_quote;
+
+    fn parse_args(tokens: proc_macro2::TokenStream) -> Result<Args, syn::Error> {
+        let mut args = Args::default();
+        syn::meta::parser(|meta| args.parse_meta(meta)).parse2(tokens)?;
+        Ok(args)
+    }
+
+    #[test]
+    fn args() {
+        let args = parse_args(quote::quote!(
+            checks = "ptrace, tracerpid, timing, integrity, injection, parent, sigtrap, guardian, dumpable",
+            placement = "exit"
+        ))
+        .unwrap();
+        assert_eq!(
+            vec![
+                Check::Ptrace,
+                Check::TracerPid,
+                Check::Timing,
+                Check::Integrity,
+                Check::Injection,
+                Check::Parent,
+                Check::SigTrap,
+                Check::Guardian,
+                Check::Dumpable
+            ],
+            args.checks
+        );
+        assert_eq!(vec![Placement::Exit], args.placements);
+
+        let args = parse_args(quote::quote!()).unwrap();
+        assert_eq!(vec![Check::Ptrace], args.checks);
+        assert_eq!(vec![Placement::Entry], args.placements);
+
+        let args = parse_args(quote::quote!(placement = "random")).unwrap();
+        assert_eq!(1, args.placements.len());
+
+        assert!(parse_args(quote::quote!(checks = "gdb")).is_err());
+        assert!(parse_args(quote::quote!(checks = "ptrace,")).is_err());
+        assert!(parse_args(quote::quote!(placement = "middle")).is_err());
+        assert!(parse_args(quote::quote!(when = "entry")).is_err());
+    }
+
+    #[test]
+    fn expansion() {
+        let args = Args {
+            checks: vec![Check::MultiPtrace, Check::TracerPid],
+            placements: vec![Placement::Entry, Placement::Exit],
+        };
+        let out = expand(
+            args,
+            parse_quote!(
+                fn f() -> u32 {
+                    1
+                }
+            ),
+        )
+        .unwrap()
+        .to_string();
+
+        // Once at entry and once in the exit guard.
+        assert_eq!(2, out.matches("multi_ptraceme_or_die").count());
+        assert_eq!(2, out.matches("tracerpid_or_die").count());
+        assert!(out.contains("debug_assertions"));
+        assert!(out.contains("Drop"));
+    }
+
+    #[test]
+    fn here() {
+        let args = || Args {
+            checks: vec![
+                Check::MultiPtrace,
+                Check::TracerPid,
+                Check::Timing,
+                Check::SigTrap,
+            ],
+            placements: vec![],
+        };
+        let out = expand_here(args()).to_string();
+        assert_eq!(2, out.matches("__multi_ptraceme_react").count());
+        // At least 4 chains of 1 call for each check, every call expanded separately.
+        assert!(out.matches("__chain_ptraceme").count() >= 2 * 4);
+        assert_eq!(
+            out.matches("__chain_check").count(),
+            out.matches("let mut v").count()
+        );
+        assert_eq!(1, out.matches("tracerpid_or_die").count());
+        assert_eq!(1, out.matches("timed_or_die").count());
+        assert_eq!(1, out.matches("sigtrap_or_die").count());
+        assert_eq!(4, out.matches("debug_assertions").count());
+
+        // Shapes, constants and order change at each expansion.
+        let outs: Vec<String> = (0..16).map(|_| expand_here(args()).to_string()).collect();
+        assert!(outs.iter().all(|o| *o != out));
+        let first = |o: &String| o.find("tracerpid").unwrap() < o.find("sigtrap").unwrap();
+        assert!(outs.iter().any(first) && !outs.iter().all(first));
+    }
+
+    #[test]
+    fn critical() {
+        let out = expand_critical(parse_quote!(
+            fn f() -> u32 {
+                1
+            }
+        ))
+        .unwrap()
+        .to_string();
+
+        assert!(out.contains("scan_breakpoints_or_die"));
+        assert!(out.contains("inline (never)"));
+        assert!(out.contains("\"__start_debugoff_f_"));
+        assert!(out.contains("\"__stop_debugoff_f_"));
+
+        assert!(expand_critical(parse_quote!(
+            async fn f() {}
+        ))
+        .is_err());
+        assert!(expand_critical(parse_quote!(
+            #[inline]
+            fn f() {}
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn const_fn() {
+        assert!(expand(
+            Args::default(),
+            parse_quote!(
+                const fn f() {}
+            )
+        )
+        .is_err());
+    }
+}
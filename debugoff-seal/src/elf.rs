@@ -0,0 +1,382 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Minimal ELF reader: only the headers needed to locate sections, segments and symbols are
+// parsed. Both 32-bit and 64-bit, little and big endian files are supported.
+
+use std::fmt;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHT_DYNSYM: u32 = 11;
+const SHF_ALLOC: u64 = 2;
+const STT_FUNC: u8 = 2;
+
+const EM_386: u16 = 3;
+const EM_MIPS: u16 = 8;
+const EM_ARM: u16 = 40;
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+const EM_RISCV: u16 = 243;
+
+/// Error found while parsing an ELF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(pub String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn error<T>(msg: &str) -> Result<T, Error> {
+    Err(Error(msg.to_string()))
+}
+
+/// Section header.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    name_offset: u32,
+    pub kind: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+impl Section {
+    /// Return `true` if the section occupies memory at runtime and has content in the file.
+    pub fn is_loaded(&self) -> bool {
+        self.flags & SHF_ALLOC != 0 && self.kind != SHT_NOBITS
+    }
+}
+
+/// Program header.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub kind: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub filesz: u64,
+}
+
+/// Function symbol.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+}
+
+/// Parsed ELF file.
+pub struct Elf<'a> {
+    data: &'a [u8],
+    is64: bool,
+    little: bool,
+    pub machine: u16,
+    pub sections: Vec<Section>,
+    pub segments: Vec<Segment>,
+}
+
+impl<'a> Elf<'a> {
+    /// Parse the headers of the ELF file in `data`.
+    pub fn parse(data: &'a [u8]) -> Result<Elf<'a>, Error> {
+        if data.len() < 52 || &data[..4] != b"\x7fELF" {
+            return error("not an ELF file");
+        }
+        let is64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return error("invalid ELF class"),
+        };
+        let little = match data[5] {
+            1 => true,
+            2 => false,
+            _ => return error("invalid ELF data encoding"),
+        };
+
+        let mut elf = Elf {
+            data,
+            is64,
+            little,
+            machine: 0,
+            sections: vec![],
+            segments: vec![],
+        };
+        elf.machine = elf.u16(18)?;
+
+        let (phoff, shoff, h) = if is64 {
+            (elf.u64(32)?, elf.u64(40)?, 54)
+        } else {
+            (elf.u32(28)? as u64, elf.u32(32)? as u64, 42)
+        };
+        let phentsize = elf.u16(h)? as u64;
+        let phnum = elf.u16(h + 2)? as u64;
+        let shentsize = elf.u16(h + 4)? as u64;
+        let shnum = elf.u16(h + 6)? as u64;
+        let shstrndx = elf.u16(h + 8)? as usize;
+
+        for i in 0..phnum {
+            elf.segments
+                .push(elf.program_header(phoff + i * phentsize)?);
+        }
+        for i in 0..shnum {
+            elf.sections
+                .push(elf.section_header(shoff + i * shentsize)?);
+        }
+
+        // Resolve the section names.
+        if let Some(strtab) = elf.sections.get(shstrndx).cloned() {
+            for i in 0..elf.sections.len() {
+                let name = elf.string(&strtab, elf.sections[i].name_offset)?;
+                elf.sections[i].name = name;
+            }
+        } else if shnum > 0 {
+            return error("invalid section name table index");
+        }
+
+        Ok(elf)
+    }
+
+    /// Return the name of the architecture if it is supported by `debugoff`.
+    pub fn arch(&self) -> Option<&'static str> {
+        match (self.machine, self.is64) {
+            (EM_X86_64, true) => Some("x86_64"),
+            (EM_386, false) => Some("x86"),
+            (EM_ARM, false) => Some("arm"),
+            (EM_AARCH64, true) => Some("aarch64"),
+            (EM_RISCV, true) => Some("riscv64"),
+            (EM_MIPS, false) => Some("mips"),
+            (EM_MIPS, true) => Some("mips64"),
+            _ => None,
+        }
+    }
+
+    /// Return the section named `name`.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// Return the offset in the file of `len` bytes at virtual address `vaddr`, if they are
+    /// entirely loaded from the file by a single segment.
+    pub fn file_offset(&self, vaddr: u64, len: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .filter(|s| s.kind == PT_LOAD)
+            .find(|s| {
+                vaddr >= s.vaddr
+                    && vaddr
+                        .checked_add(len)
+                        .is_some_and(|end| end <= s.vaddr + s.filesz)
+            })
+            .map(|s| s.offset + vaddr - s.vaddr)
+    }
+
+    /// Return the function symbols (from `.symtab`, or from `.dynsym` for stripped binaries).
+    pub fn functions(&self) -> Result<Vec<Symbol>, Error> {
+        let table = self
+            .sections
+            .iter()
+            .find(|s| s.kind == SHT_SYMTAB)
+            .or_else(|| self.sections.iter().find(|s| s.kind == SHT_DYNSYM));
+        let table = match table {
+            Some(t) => t,
+            None => return Ok(vec![]),
+        };
+        let strtab = match self.sections.get(table.link as usize) {
+            Some(s) => s,
+            None => return error("invalid symbol string table index"),
+        };
+        let entsize = if table.entsize != 0 {
+            table.entsize
+        } else if self.is64 {
+            24
+        } else {
+            16
+        };
+
+        let mut symbols = vec![];
+        for i in 0..table.size / entsize {
+            let off = table.offset + i * entsize;
+            let (name, info, value, size) = if self.is64 {
+                (
+                    self.u32(off)?,
+                    self.u8(off + 4)?,
+                    self.u64(off + 8)?,
+                    self.u64(off + 16)?,
+                )
+            } else {
+                (
+                    self.u32(off)?,
+                    self.u8(off + 12)?,
+                    self.u32(off + 4)? as u64,
+                    self.u32(off + 8)? as u64,
+                )
+            };
+            if info & 0xF != STT_FUNC || value == 0 {
+                continue;
+            }
+            // The lowest bit of the address of Thumb functions is set.
+            let addr = if self.machine == EM_ARM {
+                value & !1
+            } else {
+                value
+            };
+            symbols.push(Symbol {
+                name: self.string(strtab, name)?,
+                addr,
+                size,
+            });
+        }
+        Ok(symbols)
+    }
+
+    /// Encode `v` as a 64-bit word with the byte order of the file.
+    pub fn word(&self, v: u64) -> [u8; 8] {
+        if self.little {
+            v.to_le_bytes()
+        } else {
+            v.to_be_bytes()
+        }
+    }
+
+    fn program_header(&self, off: u64) -> Result<Segment, Error> {
+        Ok(if self.is64 {
+            Segment {
+                kind: self.u32(off)?,
+                offset: self.u64(off + 8)?,
+                vaddr: self.u64(off + 16)?,
+                filesz: self.u64(off + 32)?,
+            }
+        } else {
+            Segment {
+                kind: self.u32(off)?,
+                offset: self.u32(off + 4)? as u64,
+                vaddr: self.u32(off + 8)? as u64,
+                filesz: self.u32(off + 16)? as u64,
+            }
+        })
+    }
+
+    fn section_header(&self, off: u64) -> Result<Section, Error> {
+        // The name is resolved once all the headers are parsed.
+        let name_offset = self.u32(off)?;
+        Ok(if self.is64 {
+            Section {
+                name: String::new(),
+                name_offset,
+                kind: self.u32(off + 4)?,
+                flags: self.u64(off + 8)?,
+                addr: self.u64(off + 16)?,
+                offset: self.u64(off + 24)?,
+                size: self.u64(off + 32)?,
+                link: self.u32(off + 40)?,
+                entsize: self.u64(off + 56)?,
+            }
+        } else {
+            Section {
+                name: String::new(),
+                name_offset,
+                kind: self.u32(off + 4)?,
+                flags: self.u32(off + 8)? as u64,
+                addr: self.u32(off + 12)? as u64,
+                offset: self.u32(off + 16)? as u64,
+                size: self.u32(off + 20)? as u64,
+                link: self.u32(off + 24)?,
+                entsize: self.u32(off + 36)? as u64,
+            }
+        })
+    }
+
+    /// Read the NUL terminated string at `off` in the string table `table`.
+    fn string(&self, table: &Section, off: u32) -> Result<String, Error> {
+        let start = table.offset + off as u64;
+        let bytes = self.bytes(start, table.size.saturating_sub(off as u64))?;
+        let len = bytes.iter().position(|c| *c == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    /// Return `len` bytes of the file starting at `off`.
+    pub fn bytes(&self, off: u64, len: u64) -> Result<&'a [u8], Error> {
+        let start = usize::try_from(off).map_err(|_| Error("offset too large".into()))?;
+        let len = usize::try_from(len).map_err(|_| Error("length too large".into()))?;
+        match start.checked_add(len) {
+            Some(end) if end <= self.data.len() => Ok(&self.data[start..end]),
+            _ => error("truncated ELF file"),
+        }
+    }
+
+    fn u8(&self, off: u64) -> Result<u8, Error> {
+        Ok(self.bytes(off, 1)?[0])
+    }
+
+    fn u16(&self, off: u64) -> Result<u16, Error> {
+        let b: [u8; 2] = self.bytes(off, 2)?.try_into().unwrap();
+        Ok(if self.little {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, off: u64) -> Result<u32, Error> {
+        let b: [u8; 4] = self.bytes(off, 4)?.try_into().unwrap();
+        Ok(if self.little {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    fn u64(&self, off: u64) -> Result<u64, Error> {
+        let b: [u8; 8] = self.bytes(off, 8)?.try_into().unwrap();
+        Ok(if self.little {
+            u64::from_le_bytes(b)
+        } else {
+            u64::from_be_bytes(b)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Elf;
+
+    #[test]
+    fn current_exe() {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let elf = Elf::parse(&data).unwrap();
+        assert!(elf.arch().is_some());
+
+        let text = elf.section(".text").unwrap();
+        assert!(text.is_loaded());
+        assert_eq!(Some(text.offset), elf.file_offset(text.addr, text.size));
+
+        // Test binaries are stripped only in release (`strip` of the release profile).
+        let functions = elf.functions().unwrap();
+        assert_eq!(
+            elf.section(".symtab").is_some(),
+            functions.iter().any(|f| f.name.contains("current_exe"))
+        );
+        assert!(cfg!(not(debug_assertions)) || elf.section(".symtab").is_some());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Elf::parse(b"not an ELF file at all, not an ELF file at all, ...").is_err());
+
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        assert!(Elf::parse(&data[..64]).is_err());
+    }
+}
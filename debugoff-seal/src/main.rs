@@ -0,0 +1,346 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Post-link tool writing the integrity seal of a binary built with `debugoff`.
+//!
+//! The tool computes the checksums of the selected sections, functions or address ranges of an
+//! ELF file and writes them in the seal section reserved by the library (see
+//! `debugoff::integrity`). It must be run after the last step modifying the binary (e.g., after
+//! stripping it):
+//!
+//! ```text
+//! cargo build --release
+//! debugoff-seal target/release/app
+//! ```
+//!
+//! By default, `.text` and the sections of the functions marked as `critical` (`debugoff_*`)
+//! are sealed.
+
+mod elf;
+
+use debugoff::integrity::{checksum, MAX_REGIONS, SEAL_MAGIC, SEAL_SECTION};
+use elf::{Elf, Error};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Usage: debugoff-seal [OPTIONS] <BINARY>
+
+Write the integrity seal of a binary built with debugoff.
+
+Options:
+  -s, --section <NAME>     Seal the section NAME (a trailing `*` matches a prefix)
+  -f, --function <SYMBOL>  Seal the function SYMBOL (requires the symbol table)
+  -r, --range <ADDR:LEN>   Seal LEN bytes at virtual address ADDR
+  -o, --output <FILE>      Write the sealed binary to FILE instead of modifying BINARY
+  -h, --help               Print this help
+
+Without selection options, `.text` and `debugoff_*` are sealed.";
+
+// Sections sealed when nothing is selected.
+const DEFAULT_SECTIONS: &[&str] = &[".text", "debugoff_*"];
+
+// Size of the header of the seal (magic, virtual address and count) and of each region.
+const HEADER_SIZE: usize = 32;
+const REGION_SIZE: usize = 24;
+
+/// Part of the binary to seal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Section(String),
+    Function(String),
+    Range(u64, u64),
+}
+
+/// Region written in the seal.
+#[derive(Debug)]
+struct Region {
+    vaddr: u64,
+    len: u64,
+    checksum: u64,
+    source: String,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    selectors: Vec<Selector>,
+}
+
+fn parse_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_range(s: &str) -> Option<Selector> {
+    let (addr, len) = s.split_once(':')?;
+    Some(Selector::Range(parse_number(addr)?, parse_number(len)?))
+}
+
+/// Parse the command line arguments. Returns `None` when the help has been requested.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, Error> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| Error(format!("missing value for {}", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-s" | "--section" => opts.selectors.push(Selector::Section(value(&arg)?)),
+            "-f" | "--function" => opts.selectors.push(Selector::Function(value(&arg)?)),
+            "-r" | "--range" => {
+                let v = value(&arg)?;
+                opts.selectors
+                    .push(parse_range(&v).ok_or_else(|| Error(format!("invalid range `{}`", v)))?);
+            }
+            "-o" | "--output" => opts.output = Some(value(&arg)?.into()),
+            _ if arg.starts_with('-') => return Err(Error(format!("unknown option `{}`", arg))),
+            _ if opts.input.is_none() => opts.input = Some(arg.into()),
+            _ => return Err(Error(format!("unexpected argument `{}`", arg))),
+        }
+    }
+
+    if opts.input.is_none() {
+        return Err(Error("missing binary".into()));
+    }
+    if opts.selectors.is_empty() {
+        opts.selectors = DEFAULT_SECTIONS
+            .iter()
+            .map(|s| Selector::Section(s.to_string()))
+            .collect();
+    }
+    Ok(Some(opts))
+}
+
+/// Return the regions (virtual address, length and description) selected by `selector`.
+fn resolve(elf: &Elf, selector: &Selector) -> Result<Vec<(u64, u64, String)>, Error> {
+    match selector {
+        Selector::Section(name) => {
+            let (prefix, exact) = match name.strip_suffix('*') {
+                Some(prefix) => (prefix, false),
+                None => (name.as_str(), true),
+            };
+            let sections: Vec<_> = elf
+                .sections
+                .iter()
+                .filter(|s| s.is_loaded() && s.size > 0)
+                .filter(|s| s.name == *name || (!exact && s.name.starts_with(prefix)))
+                .map(|s| (s.addr, s.size, format!("section {}", s.name)))
+                .collect();
+            // A prefix may legitimately match nothing (e.g., no critical functions).
+            if exact && sections.is_empty() {
+                return Err(Error(format!("section `{}` not found", name)));
+            }
+            Ok(sections)
+        }
+        Selector::Function(name) => {
+            let functions = elf.functions()?;
+            if functions.is_empty() {
+                return Err(Error(format!(
+                    "cannot find function `{}`: no symbol table (stripped binary?), use \
+                     --section or --range",
+                    name
+                )));
+            }
+            match functions.iter().find(|f| f.name == *name && f.size > 0) {
+                Some(f) => Ok(vec![(f.addr, f.size, format!("function {}", name))]),
+                None => Err(Error(format!("function `{}` not found", name))),
+            }
+        }
+        Selector::Range(addr, len) => Ok(vec![(*addr, *len, format!("range {:#x}", addr))]),
+    }
+}
+
+/// Compute the regions selected by `selectors` and write them in the seal of the ELF file in
+/// `data`.
+fn seal(data: &mut [u8], selectors: &[Selector]) -> Result<Vec<Region>, Error> {
+    let elf = Elf::parse(data)?;
+    if elf.arch().is_none() {
+        return Err(Error(format!(
+            "unsupported architecture (ELF machine {})",
+            elf.machine
+        )));
+    }
+
+    let seal = elf
+        .section(SEAL_SECTION)
+        .ok_or_else(|| {
+            Error(format!(
+                "section `{}` not found (is `debugoff::integrity` used by the binary?)",
+                SEAL_SECTION
+            ))
+        })?
+        .clone();
+    let size = HEADER_SIZE + MAX_REGIONS * REGION_SIZE;
+    if (seal.size as usize) < size || elf.bytes(seal.offset, 16)? != SEAL_MAGIC {
+        return Err(Error(format!("invalid `{}` section", SEAL_SECTION)));
+    }
+
+    let mut regions = vec![];
+    for selector in selectors {
+        for (vaddr, len, source) in resolve(&elf, selector)? {
+            if len == 0 {
+                return Err(Error(format!("{}: empty region", source)));
+            }
+            let offset = elf
+                .file_offset(vaddr, len)
+                .ok_or_else(|| Error(format!("{}: not entirely loaded from the file", source)))?;
+            // The seal is modified after the checksums are computed.
+            if vaddr < seal.addr + seal.size && seal.addr < vaddr + len {
+                return Err(Error(format!("{}: overlaps the seal", source)));
+            }
+            regions.push(Region {
+                vaddr,
+                len,
+                checksum: checksum(elf.bytes(offset, len)?),
+                source,
+            });
+        }
+    }
+    if regions.is_empty() {
+        return Err(Error("no region selected".into()));
+    }
+    if regions.len() > MAX_REGIONS {
+        return Err(Error(format!(
+            "{} regions selected, at most {} are supported",
+            regions.len(),
+            MAX_REGIONS
+        )));
+    }
+
+    let mut content = vec![0u8; size];
+    content[..16].copy_from_slice(&SEAL_MAGIC);
+    content[16..24].copy_from_slice(&elf.word(seal.addr));
+    content[24..32].copy_from_slice(&elf.word(regions.len() as u64));
+    for (i, r) in regions.iter().enumerate() {
+        let off = HEADER_SIZE + i * REGION_SIZE;
+        content[off..off + 8].copy_from_slice(&elf.word(r.vaddr));
+        content[off + 8..off + 16].copy_from_slice(&elf.word(r.len));
+        content[off + 16..off + 24].copy_from_slice(&elf.word(r.checksum));
+    }
+
+    let start = seal.offset as usize;
+    data[start..start + size].copy_from_slice(&content);
+    Ok(regions)
+}
+
+fn run(opts: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let input = opts.input.unwrap();
+    let mut data = fs::read(&input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let regions =
+        seal(&mut data, &opts.selectors).map_err(|e| format!("{}: {}", input.display(), e))?;
+
+    let output = opts.output.unwrap_or_else(|| input.clone());
+    fs::write(&output, &data).map_err(|e| format!("{}: {}", output.display(), e))?;
+    if output != input {
+        fs::set_permissions(&output, fs::metadata(&input)?.permissions())?;
+    }
+
+    println!("{}: sealed {} region(s)", output.display(), regions.len());
+    for r in regions {
+        println!(
+            "  {:#012x} {:>8} bytes  {:#018x}  {}",
+            r.vaddr, r.len, r.checksum, r.source
+        );
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("debugoff-seal: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("debugoff-seal: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{parse_args, seal, Selector, DEFAULT_SECTIONS};
+    use crate::elf::Elf;
+    use debugoff::integrity::{checksum, SEAL_SECTION};
+
+    fn args(s: &str) -> impl Iterator<Item = String> + '_ {
+        s.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn arguments() {
+        let opts = parse_args(args("-s .text -r 0x1000:64 app -o out"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("app".into()), opts.input);
+        assert_eq!(Some("out".into()), opts.output);
+        assert_eq!(
+            vec![
+                Selector::Section(".text".into()),
+                Selector::Range(0x1000, 64)
+            ],
+            opts.selectors
+        );
+
+        let opts = parse_args(args("app")).unwrap().unwrap();
+        assert_eq!(DEFAULT_SECTIONS.len(), opts.selectors.len());
+
+        assert!(parse_args(args("-h")).unwrap().is_none());
+        assert!(parse_args(args("")).is_err());
+        assert!(parse_args(args("app -r 0x1000")).is_err());
+        assert!(parse_args(args("app -s")).is_err());
+        assert!(parse_args(args("app --strip")).is_err());
+    }
+
+    #[test]
+    fn seal_current_exe() {
+        // Keep the seal section in the test binary.
+        let _ = debugoff::integrity::verify_checked();
+
+        let mut data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let regions = seal(&mut data, &[Selector::Section(".text".into())]).unwrap();
+        assert_eq!(1, regions.len());
+
+        let elf = Elf::parse(&data).unwrap();
+        let text = elf.section(".text").unwrap();
+        let s = elf.section(SEAL_SECTION).unwrap();
+        let word = |off: u64| {
+            let b = elf.bytes(s.offset + off, 8).unwrap();
+            u64::from_ne_bytes(b.try_into().unwrap())
+        };
+        assert_eq!(s.addr, word(16));
+        assert_eq!(1, word(24));
+        assert_eq!(text.addr, word(32));
+        assert_eq!(text.size, word(40));
+        assert_eq!(
+            checksum(elf.bytes(text.offset, text.size).unwrap()),
+            word(48)
+        );
+
+        // The seal cannot cover itself.
+        let own = Selector::Range(s.addr, 8);
+        assert!(seal(&mut data, &[own]).is_err());
+        assert!(seal(&mut data, &[Selector::Section(".nothing".into())]).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use std::time::SystemTime;
+
+// The function is scanned for software breakpoints every time it is entered (only for Linux and
+// when building in release mode).
+#[debugoff::critical]
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+#[debugoff::protect(checks = "ptrace")]
+pub fn main() {
+    println!("Time: {}", now());
+    println!("Time: {}", now());
+
+    println!("Example complete!");
+}
@@ -0,0 +1,30 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// The binary must be sealed after linking, e.g.:
+//
+//   cargo build --release --example simple_integrity
+//   cargo run -p debugoff-seal -- target/release/examples/simple_integrity
+
+use std::time::SystemTime;
+
+fn main() {
+    // Call only for Linux and when building in release mode
+    #[cfg(target_os = "linux")]
+    #[cfg(not(debug_assertions))]
+    debugoff::integrity::verify_or_die();
+
+    println!(
+        "Time: {}",
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    );
+
+    println!("Example complete!");
+}
@@ -0,0 +1,28 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use std::time::SystemTime;
+
+fn main() {
+    // The name is obfuscated at compile time.
+    debugoff::deny_parent!("debugoff-denied");
+
+    // Call only for Linux and when building in release mode
+    #[cfg(target_os = "linux")]
+    #[cfg(not(debug_assertions))]
+    debugoff::parent_or_die();
+
+    println!(
+        "Time: {}",
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    );
+
+    println!("Example complete!");
+}
@@ -0,0 +1,24 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use std::time::SystemTime;
+
+// The checks are inserted only for Linux and when building in release mode.
+#[debugoff::protect(checks = "multi_ptrace,tracerpid", placement = "entry,random")]
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+#[debugoff::protect(checks = "ptrace", placement = "exit")]
+pub fn main() {
+    println!("Time: {}", now());
+
+    println!("Example complete!");
+}
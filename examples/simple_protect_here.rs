@@ -0,0 +1,26 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use std::time::SystemTime;
+
+pub fn main() {
+    // The checks are inserted only for Linux and when building in release mode. Each call site
+    // gets its own loop shapes, constants and check order.
+    debugoff::protect_here!(checks = "multi_ptrace,tracerpid,parent");
+
+    println!(
+        "Time: {}",
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    );
+
+    debugoff::protect_here!();
+
+    println!("Example complete!");
+}
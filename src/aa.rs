@@ -5,14 +5,27 @@
 // - GPL-3.0 when "obfuscate" feature is enabled;
 // - MIT when "obfuscate" feature IS NOT enabled;
 
-// use std::mem::MaybeUninit;
-// use std::sync::Mutex;
-use std::cell::{RefCell, RefMut};
-// use std::sync::Once;
+use crate::errno::{Errno, SyscallResult};
+use crate::policy::the_end;
+#[cfg(feature = "std")]
+use crate::probe::is_supported;
+use crate::procfs::read_file;
+#[cfg(not(feature = "std"))]
+use crate::sync::SpinLock;
 use const_random::const_random;
+use core::ops::DerefMut;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+use core::{mem, ptr};
 use crunchy::*;
 #[cfg(feature = "obfuscate")]
 use goldberg::*;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
 use unroll::*;
 
 #[allow(non_camel_case_types)]
@@ -24,55 +37,239 @@ enum PtraceRequest {
 struct PtraceState {
     traceme_done: bool,
     traceme_ctr: u64,
+    // TID of the thread, registered in `TRACED_TIDS` when `PTRACE_TRACEME` completes (0 if not
+    // registered).
+    tid: u32,
+    // PID of the process in which `PTRACE_TRACEME` completed. A different PID means that the
+    // thread-local state has been inherited through `fork()`.
+    pid: u32,
 }
 
+// TIDs of the threads that completed `PTRACE_TRACEME`. The set is updated when a thread completes
+// `PTRACE_TRACEME` and when it exits. It is used by the TracerPid check to recognize the tracer
+// set up by `PTRACE_TRACEME` and, only if the process-global state is enabled (see
+// `enable_global_state`), by threads whose thread-local state is not yet initialized.
+//
+// Without the standard library there is no thread-local state: the set is always used and it
+// never shrinks, as the exit of a thread cannot be observed. When it is full, the threads that
+// complete `PTRACE_TRACEME` cannot be recorded and the checks report
+// `DebugOffError::ThreadLimit`.
+static GLOBAL_STATE: AtomicBool = AtomicBool::new(false);
+#[cfg(feature = "std")]
+static TRACED_TIDS: Mutex<TidSet> = Mutex::new(BTreeSet::new());
+#[cfg(not(feature = "std"))]
+static TRACED_TIDS: SpinLock<TidSet> = SpinLock::new(TidSet::new());
+// PID of the process owning the TIDs in `TRACED_TIDS`.
+static TRACED_PID: AtomicU32 = AtomicU32::new(0);
+
 const SRSIZE: usize = 10;
 
+const CLOCK_REALTIME: usize = 0;
+const GRND_NONBLOCK: usize = 1;
+const PR_GET_AUXV: usize = 0x41555856;
+const AT_NULL: usize = 0;
+const AT_RANDOM: usize = 25;
+// Size of the buffer holding the auxiliary vector (the entries after it are ignored).
+const AUXV_SIZE: usize = 1024;
+
 #[derive(Debug)]
 struct Aa {
-    // ptrace_state: Mutex<PtraceState>,
     ptrace_state: PtraceState,
     r: Rand,
     sr: [u32; SRSIZE],
 }
 
+#[cfg(feature = "std")]
+type TidSet = BTreeSet<u32>;
+
+// Maximum number of threads recorded in `TRACED_TIDS` without the standard library, over the
+// whole lifetime of the process.
+#[cfg(not(feature = "std"))]
+const MAX_TRACED_TIDS: usize = 64;
+
+/// Fixed-size set of TIDs, used instead of a `BTreeSet` without the standard library.
+#[cfg(not(feature = "std"))]
+struct TidSet {
+    tids: [u32; MAX_TRACED_TIDS],
+    len: usize,
+}
+
+#[cfg(not(feature = "std"))]
+impl TidSet {
+    const fn new() -> TidSet {
+        TidSet {
+            tids: [0u32; MAX_TRACED_TIDS],
+            len: 0,
+        }
+    }
+
+    fn contains(&self, tid: &u32) -> bool {
+        self.tids[..self.len].contains(tid)
+    }
+
+    /// Add `tid` to the set. Returns `false` if it is already present.
+    ///
+    /// Returns [`DebugOffError::ThreadLimit`](crate::DebugOffError::ThreadLimit) if the set is
+    /// full.
+    fn try_insert(&mut self, tid: u32) -> Result<bool, crate::DebugOffError> {
+        if self.contains(&tid) {
+            return Ok(false);
+        }
+        if self.len == MAX_TRACED_TIDS {
+            return Err(crate::DebugOffError::ThreadLimit);
+        }
+        self.tids[self.len] = tid;
+        self.len += 1;
+        Ok(true)
+    }
+
+    fn remove(&mut self, tid: &u32) -> bool {
+        match self.tids[..self.len].iter().position(|t| t == tid) {
+            Some(idx) => {
+                self.len -= 1;
+                self.tids[idx] = self.tids[self.len];
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(feature = "std")]
 thread_local!(static AA: RefCell<Aa> = RefCell::new(Aa::new()));
 
-impl Aa {
-    // fn ptrace_singleton() -> &'static Aa {
-    //     static mut SINGLETON: MaybeUninit<Aa> = MaybeUninit::uninit();
-    //     static ONCE: Once = Once::new();
-
-    //     unsafe {
-    //         ONCE.call_once(|| {
-    //             let singleton = Aa {
-    //                 ptrace_state: Mutex::new(PtraceState {
-    //                     traceme_done: false,
-    //                     traceme_ctr: 0,
-    //                 }),
-    //             };
-    //             SINGLETON.write(singleton);
-    //         });
-
-    //         SINGLETON.assume_init_ref()
-    //     }
-    // }
+// Without the standard library, the state is shared by all the threads (and initialized at the
+// first check).
+#[cfg(not(feature = "std"))]
+static AA: SpinLock<Option<Aa>> = SpinLock::new(None);
 
-    fn new() -> Aa {
-        let tmp = std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH);
+/// Run `f` with the state of the current thread.
+#[cfg(feature = "std")]
+#[inline(always)]
+fn with_aa<R>(f: impl FnOnce(&mut Aa) -> R) -> R {
+    AA.with(|aa| f(&mut aa.borrow_mut()))
+}
 
-        #[cfg(feature = "obfuscate")]
-        goldberg_stmts! {
-            let r: u128 = match tmp {
-                Ok(x) => x.as_nanos(),
-                _ => 0u128,
-            };
+/// Run `f` with the state of the current thread.
+///
+/// The state is shared by all the threads: the TID of the caller is looked up in `TRACED_TIDS`.
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn with_aa<R>(f: impl FnOnce(&mut Aa) -> R) -> R {
+    let mut aa = AA.lock();
+    let aa = aa.get_or_insert_with(Aa::new);
+    aa.ptrace_state.traceme_done = false;
+    f(aa)
+}
+
+/// Time elapsed since the epoch, read with a raw `clock_gettime` system call.
+#[inline(always)]
+fn since_epoch() -> Result<Duration, Errno> {
+    // `struct timespec` made of two `long`.
+    let mut ts = [0isize; 2];
+    unsafe {
+        crate::arch::syscall2(
+            crate::arch::SysNo::SYS_CLOCK_GETTIME,
+            CLOCK_REALTIME,
+            ts.as_mut_ptr() as usize,
+        )
+    }
+    .map(|_| Duration::new(ts[0] as u64, ts[1] as u32))
+}
+
+/// Find the value of the entry `key` in the auxiliary vector `auxv`.
+fn auxv_value(auxv: &[u8], key: usize) -> Option<usize> {
+    const WORD: usize = mem::size_of::<usize>();
+
+    auxv.chunks_exact(2 * WORD)
+        .map(|e| {
+            let (k, v) = e.split_at(WORD);
+            (
+                usize::from_ne_bytes(k.try_into().unwrap_or_default()),
+                usize::from_ne_bytes(v.try_into().unwrap_or_default()),
+            )
+        })
+        .take_while(|(k, _)| *k != AT_NULL)
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+/// Address of the 16 random bytes placed by the kernel on the initial stack (`AT_RANDOM`).
+///
+/// The auxiliary vector is read with `prctl(PR_GET_AUXV, ...)` (Linux 6.4) or, on older kernels,
+/// from `/proc/self/auxv` (which cannot be read by a non-dumpable process).
+fn at_random() -> Option<usize> {
+    let mut auxv = [0u8; AUXV_SIZE];
+    let res = unsafe {
+        crate::arch::syscall5(
+            crate::arch::SysNo::SYS_PRCTL,
+            PR_GET_AUXV,
+            auxv.as_mut_ptr() as usize,
+            auxv.len(),
+            0,
+            0,
+        )
+    };
+    let len = match res {
+        Ok(len) => len.min(auxv.len()),
+        Err(_) => read_file(b"/proc/self/auxv\0", &mut auxv).ok()?,
+    };
+    auxv_value(&auxv[..len], AT_RANDOM).filter(|addr| *addr != 0)
+}
+
+/// Draw the seed of the generator from the kernel.
+///
+/// The seed is read with a raw `getrandom` system call. When it is not available (kernels older
+/// than 3.17, seccomp filters, entropy pool not initialized yet), the bytes at `AT_RANDOM` are
+/// used instead: they are shared by all the threads (and inherited through `fork()`), so they are
+/// mixed with the TID and the time. The time alone is the last resort.
+fn seed() -> [u32; 4] {
+    let mut seed = [0u32; 4];
+    let res = unsafe {
+        crate::arch::syscall3(
+            crate::arch::SysNo::SYS_GETRANDOM,
+            seed.as_mut_ptr() as usize,
+            mem::size_of_val(&seed),
+            GRND_NONBLOCK,
+        )
+    };
+
+    if res != Ok(mem::size_of_val(&seed)) {
+        if let Some(addr) = at_random() {
+            // Safety: the kernel guarantees 16 bytes at `AT_RANDOM`, valid for the whole process
+            // lifetime.
+            seed = unsafe { ptr::read_unaligned(addr as *const [u32; 4]) };
         }
-        #[cfg(not(feature = "obfuscate"))]
-        let r: u128 = match tmp {
-            Ok(x) => x.as_nanos(),
-            _ => 0u128,
-        };
+        let t = since_epoch().map(|d| d.as_nanos()).unwrap_or(0);
+        seed[0] ^= t as u32;
+        seed[1] ^= (t >> 32) as u32;
+        seed[2] ^= gettid();
+        seed[3] ^= getpid();
+    }
+
+    seed
+}
+
+// Without the standard library the host cannot be probed: the failures of every check are
+// reacted to.
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn is_supported(_check: crate::Check) -> bool {
+    true
+}
+
+impl Aa {
+    fn new() -> Aa {
+        let seed = seed();
 
         #[cfg(feature = "obfuscate")]
         goldberg_stmts! {
@@ -80,8 +277,10 @@ impl Aa {
                 ptrace_state : PtraceState {
                     traceme_done: false,
                     traceme_ctr: 0u64,
+                    tid: 0u32,
+                    pid: 0u32,
                 },
-                r: Rand::new(r as u32),
+                r: Rand::new(seed),
                 sr: [
                     const_random!(u32),
                     const_random!(u32),
@@ -101,8 +300,10 @@ impl Aa {
             ptrace_state: PtraceState {
                 traceme_done: false,
                 traceme_ctr: 0u64,
+                tid: 0u32,
+                pid: 0u32,
             },
-            r: Rand::new(r as u32),
+            r: Rand::new(seed),
             sr: [
                 const_random!(u32),
                 const_random!(u32),
@@ -117,13 +318,120 @@ impl Aa {
             ],
         }
     }
+
+    /// Return `true` if the current thread already completed `PTRACE_TRACEME`.
+    ///
+    /// The thread-local state is checked first. When the process-global state is enabled and the
+    /// thread-local state is not initialized, the TID of the thread is looked up in the global
+    /// state.
+    ///
+    /// A state inherited from the parent process after a `fork()` is discarded: the child is a
+    /// new process which is not traced yet.
+    #[inline(always)]
+    fn traceme_done(&mut self) -> bool {
+        let pid = getpid();
+
+        if self.ptrace_state.traceme_done {
+            if self.ptrace_state.pid == pid {
+                return true;
+            }
+            self.ptrace_state.traceme_done = false;
+            self.ptrace_state.tid = 0;
+        }
+
+        if cfg!(not(feature = "std")) || GLOBAL_STATE.load(Ordering::Acquire) {
+            let tid = gettid();
+            if lock_traced_tids().contains(&tid) {
+                self.ptrace_state.traceme_done = true;
+                self.ptrace_state.tid = tid;
+                self.ptrace_state.pid = pid;
+            }
+        }
+
+        self.ptrace_state.traceme_done
+    }
+
+    /// Record that the current thread completed `PTRACE_TRACEME`.
+    ///
+    /// Without the standard library, returns
+    /// [`DebugOffError::ThreadLimit`](crate::DebugOffError::ThreadLimit) if the thread cannot be
+    /// recorded: its next checks would fail as if it was traced by a debugger.
+    #[inline(always)]
+    fn set_traceme_done(&mut self) -> Result<(), crate::DebugOffError> {
+        let tid = gettid();
+        #[cfg(feature = "std")]
+        lock_traced_tids().insert(tid);
+        #[cfg(not(feature = "std"))]
+        lock_traced_tids().try_insert(tid)?;
+        self.ptrace_state.traceme_done = true;
+        self.ptrace_state.pid = getpid();
+        self.ptrace_state.tid = tid;
+        Ok(())
+    }
+}
+
+impl Drop for Aa {
+    fn drop(&mut self) {
+        // The thread is exiting: its TID can be reused by a new thread.
+        if self.ptrace_state.tid != 0 && self.ptrace_state.pid == getpid() {
+            lock_traced_tids().remove(&self.ptrace_state.tid);
+        }
+    }
+}
+
+/// Lock the global state, discarding the TIDs inherited from the parent process after a
+/// `fork()`.
+fn lock_traced_tids() -> impl DerefMut<Target = TidSet> {
+    #[cfg(feature = "std")]
+    let mut tids = TRACED_TIDS.lock().unwrap_or_else(|e| e.into_inner());
+    #[cfg(not(feature = "std"))]
+    let mut tids = TRACED_TIDS.lock();
+
+    let pid = getpid();
+    if TRACED_PID.swap(pid, Ordering::AcqRel) != pid {
+        tids.clear();
+    }
+
+    tids
+}
+
+/// Return `true` if the thread `tid` of the current process completed `PTRACE_TRACEME` through
+/// one of the checks (and, thus, its tracer is the parent process).
+#[cfg(feature = "std")]
+pub(crate) fn self_traced(tid: u32) -> bool {
+    lock_traced_tids().contains(&tid)
+}
+
+#[inline(always)]
+pub(crate) fn getpid() -> u32 {
+    unsafe { crate::arch::syscall0(crate::arch::SysNo::SYS_GETPID).unwrap_or(0) as u32 }
+}
+
+#[inline(always)]
+pub(crate) fn gettid() -> u32 {
+    unsafe { crate::arch::syscall0(crate::arch::SysNo::SYS_GETTID).unwrap_or(0) as u32 }
+}
+
+/// Enable the process-global detection state.
+///
+/// By default, the state used by the `ptrace` checks is kept only in thread-local storage. When
+/// the global state is enabled, the TIDs of the threads that completed `PTRACE_TRACEME` are also
+/// recorded in a process-wide registry (protected by a lock and updated only the first time a
+/// thread runs a check and when the thread exits). A thread whose thread-local state is lost or
+/// not yet initialized is then judged against the registry instead of fresh state. The checks
+/// keep running lock-free once the thread-local state is initialized.
+///
+/// The global state should be enabled at startup, before any check is executed. Without the `std`
+/// feature, the global state is always used.
+pub fn enable_global_state() {
+    GLOBAL_STATE.store(true, Ordering::Release);
 }
 
 /// Sets the process as traceable, as with `ptrace(PTRACE_TRACEME, ...)`
 #[inline(always)]
-fn ptraceme() -> Result<(), crate::DebugOffErr> {
+fn ptraceme() -> Result<(), crate::DebugOffError> {
     #[cfg(feature = "obfuscate")]
-    let res: usize = unsafe {
+    let res: SyscallResult = unsafe {
         crate::arch::syscall4(
             crate::arch::SysNo::SYS_PTRACE,
             PtraceRequest::PTRACE_TRACEME as usize,
@@ -133,7 +441,7 @@ fn ptraceme() -> Result<(), crate::DebugOffErr> {
         )
     };
     #[cfg(not(feature = "obfuscate"))]
-    let res: usize = unsafe {
+    let res: SyscallResult = unsafe {
         crate::arch::syscall4(
             crate::arch::SysNo::SYS_PTRACE,
             PtraceRequest::PTRACE_TRACEME as usize,
@@ -143,11 +451,13 @@ fn ptraceme() -> Result<(), crate::DebugOffErr> {
         )
     };
 
+    // EPERM is returned when the thread is already traced. Any other error (e.g., ENOSYS returned
+    // by a seccomp filter) is reported as it is.
     match res {
-        0 => Ok(()),
-        _ => Err(crate::DebugOffErr {
-            err_type: crate::DebugOffErrType::AlreadyTraced,
-        }),
+        Ok(0) => Ok(()),
+        Ok(r) => Err(crate::DebugOffError::UnexpectedReturn(r as isize)),
+        Err(Errno::EPERM) => Err(crate::DebugOffError::AlreadyTraced),
+        Err(e) => Err(crate::DebugOffError::Syscall(e)),
     }
 }
 
@@ -156,45 +466,46 @@ fn ptraceme() -> Result<(), crate::DebugOffErr> {
 /// This function can be called multiple times.
 ///
 /// At the first invocation, the function expects a return value of 0 from `ptrace(PTRACE_TRACEME, ...)`.
-/// In subsequent calls, `ptrace(PTRACE_TRACEME, ...)` should return -1.
+/// In subsequent calls, `ptrace(PTRACE_TRACEME, ...)` should fail with `EPERM`.
 ///
-/// If the above is not satisfied, the function calls `exit_group(0)`.
+/// If the above is not satisfied, the function returns an error describing what went wrong and
+/// leaves to the caller the decision on how to react. See [`ptraceme_or_die`] for a variant that
+/// terminates the process instead.
 ///
 /// To be more effective, the function should be called at least once for each thread.
 ///
+/// The state inherited by a child process created with `fork()` is discarded at the first check
+/// executed in the child (which is not traced yet), so the function can be used by pre-forking
+/// servers and by daemons that double-fork.
+///
 /// ## Examples
 ///
 /// ```rust
-/// // Import only on Linux and for "release builds"
-/// #[cfg(target_os = "linux")]
-/// #[cfg(not(debug_assertions))]
-/// use debugoff;
-///
 /// // Call only on Linux and for "release" builds.
 /// #[cfg(target_os = "linux")]
 /// #[cfg(not(debug_assertions))]
-/// debugoff::ptraceme_or_die();
+/// if debugoff::ptraceme_checked().is_err() {
+///     println!("Debugger detected");
+/// }
 /// ```
 #[inline(always)]
-pub fn ptraceme_or_die() {
+pub fn ptraceme_checked() -> Result<(), crate::DebugOffError> {
     let res = ptraceme();
-    // println!("{:?}", res);
 
     // The first time this function is called, res should be Ok(_). Subsequent calls should
     // return Err(_)
     #[cfg(feature = "obfuscate")]
-    AA.with(|f| {
-        let mut aa: RefMut<Aa> = f.borrow_mut();
-        match aa.ptrace_state.traceme_done {
+    return with_aa(|aa| {
+        match aa.traceme_done() {
             false => match res {
-                Ok(_) => aa.ptrace_state.traceme_done = true,
-                Err(_) => the_end(),
+                Ok(_) => aa.set_traceme_done()?,
+                Err(e) => return Err(e),
+            },
+            true => match res {
+                Ok(_) => return Err(crate::DebugOffError::UnexpectedReturn(0)),
+                Err(crate::DebugOffError::AlreadyTraced) => {}
+                Err(e) => return Err(e),
             },
-            true => {
-                if res.is_ok() {
-                    the_end()
-                }
-            }
         }
 
         goldberg_stmts! {
@@ -202,24 +513,137 @@ pub fn ptraceme_or_die() {
             tmp = tmp.saturating_add(1);
             aa.ptrace_state.traceme_ctr = tmp;
         }
+
+        Ok(())
     });
     #[cfg(not(feature = "obfuscate"))]
-    AA.with(|f| {
-        let mut aa: RefMut<Aa> = f.borrow_mut();
-        match aa.ptrace_state.traceme_done {
+    with_aa(|aa| {
+        match aa.traceme_done() {
             false => match res {
-                Ok(_) => aa.ptrace_state.traceme_done = true,
-                Err(_) => the_end(),
+                Ok(_) => aa.set_traceme_done()?,
+                Err(e) => return Err(e),
             },
-            true => {
-                if res.is_ok() {
-                    the_end()
-                }
+            true => match res {
+                Ok(_) => return Err(crate::DebugOffError::UnexpectedReturn(0)),
+                Err(crate::DebugOffError::AlreadyTraced) => {}
+                Err(e) => return Err(e),
+            },
+        }
+
+        aa.ptrace_state.traceme_ctr = aa.ptrace_state.traceme_ctr.saturating_add(1);
+
+        Ok(())
+    })
+}
+
+/// Call `ptrace(PTRACE_TRACEME, ...)` one time to detect the presence of a debugger.
+///
+/// This is a wrapper around [`ptraceme_checked`]: if the check fails, the function reacts according
+/// to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+/// `exit_group(0)`).
+///
+/// To be more effective, the function should be called at least once for each thread.
+///
+/// ## Examples
+///
+/// ```rust
+/// // Import only on Linux and for "release builds"
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// use debugoff;
+///
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::ptraceme_or_die();
+/// ```
+#[inline(always)]
+pub fn ptraceme_or_die() {
+    if ptraceme_checked().is_err() && is_supported(crate::Check::Ptrace) {
+        the_end();
+    }
+}
+
+/// Call `ptrace(PTRACE_TRACEME, ...)` once in a chain of calls: a random value is drawn, recorded
+/// in `v` and added to `offset` (see [`multi_ptraceme_checked`]). The constants added with the
+/// value are combined with the `salt` of the chain.
+#[inline(always)]
+pub fn chain_ptraceme(
+    v: &mut [u32],
+    n: &mut usize,
+    offset: &mut u32,
+    salt: u32,
+) -> Result<(), crate::DebugOffError> {
+    let res = ptraceme();
+    #[cfg(feature = "obfuscate")]
+    let (r, sum) = with_aa(|aa| {
+        match aa.traceme_done() {
+            false => match res {
+                Ok(_) => aa.set_traceme_done()?,
+                Err(e) => return Err(e),
+            },
+            true => match res {
+                Ok(_) => return Err(crate::DebugOffError::UnexpectedReturn(0)),
+                Err(crate::DebugOffError::AlreadyTraced) => {}
+                Err(e) => return Err(e),
+            },
+        }
+
+        goldberg_stmts! {
+            let r: u32 = aa.r.rand();
+            let mut sum: u32 = 0;
+            for idx in 0..SRSIZE {
+                sum = sum.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE] ^ salt));
             }
+            aa.ptrace_state.traceme_ctr = aa.ptrace_state.traceme_ctr.saturating_add(1);
+            Ok((r, sum))
+        }
+    })?;
+    #[cfg(not(feature = "obfuscate"))]
+    let (r, sum) = with_aa(|aa| {
+        match aa.traceme_done() {
+            false => match res {
+                Ok(_) => aa.set_traceme_done()?,
+                Err(e) => return Err(e),
+            },
+            true => match res {
+                Ok(_) => return Err(crate::DebugOffError::UnexpectedReturn(0)),
+                Err(crate::DebugOffError::AlreadyTraced) => {}
+                Err(e) => return Err(e),
+            },
         }
 
+        let r = aa.r.rand();
+        let mut sum: u32 = 0;
+        for idx in 0..SRSIZE {
+            sum = sum.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE] ^ salt));
+        }
         aa.ptrace_state.traceme_ctr = aa.ptrace_state.traceme_ctr.saturating_add(1);
+        Ok((r, sum))
+    })?;
+
+    v[*n] = r;
+    *n += 1;
+    *offset = offset.wrapping_add(sum);
+    Ok(())
+}
+
+/// Check the `offset` computed along a chain of calls against the random values recorded in `v`.
+#[inline(always)]
+pub fn chain_check(v: &[u32], offset: u32, salt: u32) -> Result<(), crate::DebugOffError> {
+    let mut check: u32 = 0;
+    with_aa(|aa| {
+        for r in v {
+            for idx in 0..SRSIZE {
+                check = check.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE] ^ salt));
+            }
+        }
     });
+
+    if check != offset {
+        return Err(crate::DebugOffError::OffsetMismatch);
+    }
+    Ok(())
 }
 
 /// Call `ptrace(PTRACE_TRACEME, ...)` multiple times in nested loops.
@@ -228,11 +652,53 @@ pub fn ptraceme_or_die() {
 /// compilation (just remember to clean you project before compiling again: `cargo clean`).
 ///
 /// For each iteration, if the value returned by `ptrace` is not the expected one, the function
-/// calls `exit_group(0)`. If the value returned by `ptrace` is the expected one (0 at the first
-/// call in a thread and -1 thereafter) then a random value (sum of a dynamic random value and a
+/// returns an error. If the value returned by `ptrace` is the expected one (0 at the first call
+/// in a thread and -1 thereafter) then a random value (sum of a dynamic random value and a
 /// compilation time random value) is added to an `offset` value. At the end of all the iterations
-/// the `offset` value is checked. If the check fails, the function calls
-/// `exit_group(0)`
+/// the `offset` value is checked. If the check fails, the function returns
+/// [`DebugOffError::OffsetMismatch`](crate::DebugOffError::OffsetMismatch).
+///
+/// If `debugoff` is included as a dependency with `obfuscate` feature enabled, the code is even
+/// more obfuscated by [goldberg](https://crates.io/crates/goldberg).
+///
+/// This function can be called multiple times.
+/// To be more effective, the function should be called at least once for each thread.
+///
+/// ## Examples
+///
+/// ```rust
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::multi_ptraceme_checked() {
+///     println!("Debugger detected: {}", e);
+/// }
+/// ```
+#[unroll_for_loops]
+#[inline(always)]
+pub fn multi_ptraceme_checked() -> Result<(), crate::DebugOffError> {
+    unroll! {
+        for j in 0..16 {
+            // One random value per call to `ptrace` (at most 4, see the loop below).
+            let mut v = [0u32; 4];
+            let mut n: usize = 0;
+            let mut offset: u32 = 0;
+            let salt: u32 = const_random!(u32);
+            for _i in 1..((const_random!(usize) % 4) + 2) {
+                chain_ptraceme(&mut v, &mut n, &mut offset, salt)?;
+            }
+            chain_check(&v[..n], offset, salt)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Call `ptrace(PTRACE_TRACEME, ...)` multiple times in nested loops.
+///
+/// This is a wrapper around [`multi_ptraceme_checked`]: if any of the checks fails, the function
+/// reacts according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it
+/// calls `exit_group(0)`).
 ///
 /// If `debugoff` is included as a dependency with `obfuscate` feature enabled, the code is even
 /// more obfuscated by [goldberg](https://crates.io/crates/goldberg).
@@ -253,216 +719,750 @@ pub fn ptraceme_or_die() {
 /// #[cfg(not(debug_assertions))]
 /// debugoff::multi_ptraceme_or_die();
 /// ```
-#[unroll_for_loops]
 #[inline(always)]
 pub fn multi_ptraceme_or_die() {
-    unroll! {
-        for j in 0..16 {
-            let mut v: Vec<u32> = Vec::new();
-            let mut offset: u32 = 0;
-            for _i in 1..((const_random!(usize) % 4) + 2) {
-                let res = ptraceme();
-                #[cfg(feature="obfuscate")]
-                AA.with (|f| {
-                    let mut aa: RefMut<Aa> = f.borrow_mut();
-                    goldberg_stmts! {
-                        let r = aa.r.rand();
-                        match aa.ptrace_state.traceme_done {
-                            false => match res {
-                                Ok(_) => {
-                                    aa.ptrace_state.traceme_done = true;
-                                    v.push(r);
-                                    for idx in 0..SRSIZE {
-                                        offset = offset.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE]));
-                                    }
-                                },
-                                Err(_) => the_end(),
-                            },
-                            true => match res {
-                                Ok(_) => the_end(),
-                                _ => {
-                                    v.push(r);
-                                    for idx in 0..SRSIZE {
-                                        offset = offset.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE]));
-                                    }
-                                }
-                            },
-                        }
-                        aa.ptrace_state.traceme_ctr = aa.ptrace_state.traceme_ctr.saturating_add(1);
-                    }
-                });
-                #[cfg(not(feature="obfuscate"))]
-                AA.with (|f| {
-                    let mut aa: RefMut<Aa> = f.borrow_mut();
-                    let r = aa.r.rand();
-                    match aa.ptrace_state.traceme_done {
-                        false => match res {
-                            Ok(_) => {
-                                aa.ptrace_state.traceme_done = true;
-                                v.push(r);
-                                for idx in 0..SRSIZE {
-                                    offset = offset.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE]));
-                                }
-                            },
-                            Err(_) => the_end(),
-                        },
-                        true => match res {
-                            Ok(_) => the_end(),
-                            _ => {
-                                v.push(r);
-                                for idx in 0..SRSIZE {
-                                    offset = offset.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE]));
-                                }
-                            }
-                        },
-                    }
-                    aa.ptrace_state.traceme_ctr = aa.ptrace_state.traceme_ctr.saturating_add(1);
-                });
-            }
+    if multi_ptraceme_checked().is_err() && is_supported(crate::Check::MultiPtrace) {
+        the_end();
+    }
+}
+
+/// Draw a value in `range` from the random value `r` (see [`multi_ptraceme_checked!`]).
+#[doc(hidden)]
+pub const fn __pick(range: core::ops::Range<usize>, r: usize) -> usize {
+    assert!(range.start < range.end, "empty range");
+    range.start + r % (range.end - range.start)
+}
+
+/// React to the result of [`multi_ptraceme_checked!`] (see [`multi_ptraceme_or_die!`]).
+#[doc(hidden)]
+#[inline(always)]
+pub fn __multi_ptraceme_react(res: Result<(), crate::DebugOffError>) {
+    if res.is_err() && is_supported(crate::Check::MultiPtrace) {
+        the_end();
+    }
+}
+
+/// Expand `$body` `$n` times, where `$n` is an integer literal up to 64: the copy of index `$i + k`
+/// runs only if it is lower than `$count`. Each copy is a separate block (and a separate
+/// expansion of the macros it contains), so the copies do not share any code.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeat {
+    (0, $i:expr, $count:expr, $body:block) => {};
+    (1, $i:expr, $count:expr, $body:block) => {
+        if $i < $count $body
+    };
+    (2, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(1, $i, $count, $body);
+        $crate::__repeat!(1, $i + 1, $count, $body);
+    };
+    (3, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(1, $i, $count, $body);
+        $crate::__repeat!(2, $i + 1, $count, $body);
+    };
+    (4, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(2, $i, $count, $body);
+        $crate::__repeat!(2, $i + 2, $count, $body);
+    };
+    (5, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(2, $i, $count, $body);
+        $crate::__repeat!(3, $i + 2, $count, $body);
+    };
+    (6, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(3, $i, $count, $body);
+        $crate::__repeat!(3, $i + 3, $count, $body);
+    };
+    (7, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(3, $i, $count, $body);
+        $crate::__repeat!(4, $i + 3, $count, $body);
+    };
+    (8, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(4, $i, $count, $body);
+        $crate::__repeat!(4, $i + 4, $count, $body);
+    };
+    (9, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(4, $i, $count, $body);
+        $crate::__repeat!(5, $i + 4, $count, $body);
+    };
+    (10, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(5, $i, $count, $body);
+        $crate::__repeat!(5, $i + 5, $count, $body);
+    };
+    (11, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(5, $i, $count, $body);
+        $crate::__repeat!(6, $i + 5, $count, $body);
+    };
+    (12, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(6, $i, $count, $body);
+        $crate::__repeat!(6, $i + 6, $count, $body);
+    };
+    (13, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(6, $i, $count, $body);
+        $crate::__repeat!(7, $i + 6, $count, $body);
+    };
+    (14, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(7, $i, $count, $body);
+        $crate::__repeat!(7, $i + 7, $count, $body);
+    };
+    (15, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(7, $i, $count, $body);
+        $crate::__repeat!(8, $i + 7, $count, $body);
+    };
+    (16, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(8, $i, $count, $body);
+        $crate::__repeat!(8, $i + 8, $count, $body);
+    };
+    (17, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(8, $i, $count, $body);
+        $crate::__repeat!(9, $i + 8, $count, $body);
+    };
+    (18, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(9, $i, $count, $body);
+        $crate::__repeat!(9, $i + 9, $count, $body);
+    };
+    (19, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(9, $i, $count, $body);
+        $crate::__repeat!(10, $i + 9, $count, $body);
+    };
+    (20, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(10, $i, $count, $body);
+        $crate::__repeat!(10, $i + 10, $count, $body);
+    };
+    (21, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(10, $i, $count, $body);
+        $crate::__repeat!(11, $i + 10, $count, $body);
+    };
+    (22, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(11, $i, $count, $body);
+        $crate::__repeat!(11, $i + 11, $count, $body);
+    };
+    (23, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(11, $i, $count, $body);
+        $crate::__repeat!(12, $i + 11, $count, $body);
+    };
+    (24, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(12, $i, $count, $body);
+        $crate::__repeat!(12, $i + 12, $count, $body);
+    };
+    (25, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(12, $i, $count, $body);
+        $crate::__repeat!(13, $i + 12, $count, $body);
+    };
+    (26, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(13, $i, $count, $body);
+        $crate::__repeat!(13, $i + 13, $count, $body);
+    };
+    (27, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(13, $i, $count, $body);
+        $crate::__repeat!(14, $i + 13, $count, $body);
+    };
+    (28, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(14, $i, $count, $body);
+        $crate::__repeat!(14, $i + 14, $count, $body);
+    };
+    (29, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(14, $i, $count, $body);
+        $crate::__repeat!(15, $i + 14, $count, $body);
+    };
+    (30, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(15, $i, $count, $body);
+        $crate::__repeat!(15, $i + 15, $count, $body);
+    };
+    (31, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(15, $i, $count, $body);
+        $crate::__repeat!(16, $i + 15, $count, $body);
+    };
+    (32, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(16, $i, $count, $body);
+        $crate::__repeat!(16, $i + 16, $count, $body);
+    };
+    (33, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(16, $i, $count, $body);
+        $crate::__repeat!(17, $i + 16, $count, $body);
+    };
+    (34, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(17, $i, $count, $body);
+        $crate::__repeat!(17, $i + 17, $count, $body);
+    };
+    (35, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(17, $i, $count, $body);
+        $crate::__repeat!(18, $i + 17, $count, $body);
+    };
+    (36, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(18, $i, $count, $body);
+        $crate::__repeat!(18, $i + 18, $count, $body);
+    };
+    (37, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(18, $i, $count, $body);
+        $crate::__repeat!(19, $i + 18, $count, $body);
+    };
+    (38, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(19, $i, $count, $body);
+        $crate::__repeat!(19, $i + 19, $count, $body);
+    };
+    (39, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(19, $i, $count, $body);
+        $crate::__repeat!(20, $i + 19, $count, $body);
+    };
+    (40, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(20, $i, $count, $body);
+        $crate::__repeat!(20, $i + 20, $count, $body);
+    };
+    (41, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(20, $i, $count, $body);
+        $crate::__repeat!(21, $i + 20, $count, $body);
+    };
+    (42, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(21, $i, $count, $body);
+        $crate::__repeat!(21, $i + 21, $count, $body);
+    };
+    (43, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(21, $i, $count, $body);
+        $crate::__repeat!(22, $i + 21, $count, $body);
+    };
+    (44, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(22, $i, $count, $body);
+        $crate::__repeat!(22, $i + 22, $count, $body);
+    };
+    (45, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(22, $i, $count, $body);
+        $crate::__repeat!(23, $i + 22, $count, $body);
+    };
+    (46, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(23, $i, $count, $body);
+        $crate::__repeat!(23, $i + 23, $count, $body);
+    };
+    (47, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(23, $i, $count, $body);
+        $crate::__repeat!(24, $i + 23, $count, $body);
+    };
+    (48, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(24, $i, $count, $body);
+        $crate::__repeat!(24, $i + 24, $count, $body);
+    };
+    (49, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(24, $i, $count, $body);
+        $crate::__repeat!(25, $i + 24, $count, $body);
+    };
+    (50, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(25, $i, $count, $body);
+        $crate::__repeat!(25, $i + 25, $count, $body);
+    };
+    (51, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(25, $i, $count, $body);
+        $crate::__repeat!(26, $i + 25, $count, $body);
+    };
+    (52, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(26, $i, $count, $body);
+        $crate::__repeat!(26, $i + 26, $count, $body);
+    };
+    (53, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(26, $i, $count, $body);
+        $crate::__repeat!(27, $i + 26, $count, $body);
+    };
+    (54, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(27, $i, $count, $body);
+        $crate::__repeat!(27, $i + 27, $count, $body);
+    };
+    (55, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(27, $i, $count, $body);
+        $crate::__repeat!(28, $i + 27, $count, $body);
+    };
+    (56, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(28, $i, $count, $body);
+        $crate::__repeat!(28, $i + 28, $count, $body);
+    };
+    (57, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(28, $i, $count, $body);
+        $crate::__repeat!(29, $i + 28, $count, $body);
+    };
+    (58, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(29, $i, $count, $body);
+        $crate::__repeat!(29, $i + 29, $count, $body);
+    };
+    (59, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(29, $i, $count, $body);
+        $crate::__repeat!(30, $i + 29, $count, $body);
+    };
+    (60, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(30, $i, $count, $body);
+        $crate::__repeat!(30, $i + 30, $count, $body);
+    };
+    (61, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(30, $i, $count, $body);
+        $crate::__repeat!(31, $i + 30, $count, $body);
+    };
+    (62, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(31, $i, $count, $body);
+        $crate::__repeat!(31, $i + 31, $count, $body);
+    };
+    (63, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(31, $i, $count, $body);
+        $crate::__repeat!(32, $i + 31, $count, $body);
+    };
+    (64, $i:expr, $count:expr, $body:block) => {
+        $crate::__repeat!(32, $i, $count, $body);
+        $crate::__repeat!(32, $i + 32, $count, $body);
+    };
+}
 
-            let mut check: u32 = 0;
-            AA.with (|f| {
-                let aa: RefMut<Aa> = f.borrow_mut();
-                for r in v {
-                    for idx in 0..SRSIZE {
-                        check = check.wrapping_add(r.wrapping_add(aa.sr[idx % SRSIZE]));
+/// Call `ptrace(PTRACE_TRACEME, ...)` multiple times in nested loops of configurable shape.
+///
+/// Same as [`multi_ptraceme_checked`], with the number of iterations of the outer loop drawn at
+/// compile time from the `outer` range and the number of `ptrace` calls of each iteration (inner
+/// loop) drawn from the `inner` range. The ranges are written with integer literals up to 64 and
+/// do not include their end. Both loops are unrolled when the macro is expanded: every call is a
+/// separate `ptrace` site, so larger ranges make the calls harder to follow, at the cost of a
+/// larger code.
+///
+/// * Without arguments, [`multi_ptraceme_checked`] is called (`outer = 16..17, inner = 1..5`);
+/// * `small` is a size-conscious preset for embedded targets (`outer = 2..5, inner = 1..3`).
+///
+/// The counts and the constants added to the "`offset`" values are drawn with `const_random!` at
+/// each call site when the calling crate is compiled, so every call site runs different code.
+///
+/// ## Examples
+///
+/// ```rust
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::multi_ptraceme_checked!(outer = 4..32, inner = 1..8) {
+///     println!("Debugger detected: {}", e);
+/// }
+///
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if debugoff::multi_ptraceme_checked!(small).is_err() {
+///     println!("Debugger detected");
+/// }
+/// ```
+#[macro_export]
+macro_rules! multi_ptraceme_checked {
+    () => {
+        $crate::multi_ptraceme_checked()
+    };
+    (small) => {
+        $crate::multi_ptraceme_checked!(outer = 2..5, inner = 1..3)
+    };
+    (outer = $omin:tt..$omax:tt, inner = $imin:tt..$imax:tt $(,)?) => {
+        'multi: {
+            const OUTER: usize = $crate::__pick($omin..$omax, $crate::obf::const_random!(usize));
+            $crate::__repeat!($omax, 0, OUTER, {
+                const INNER: usize =
+                    $crate::__pick($imin..$imax, $crate::obf::const_random!(usize));
+                let salt: u32 = $crate::obf::const_random!(u32);
+                let mut v = [0u32; $imax];
+                let mut n: usize = 0;
+                let mut offset: u32 = 0;
+                $crate::__repeat!($imax, 0, INNER, {
+                    if let Err(e) = $crate::__chain_ptraceme(&mut v, &mut n, &mut offset, salt) {
+                        break 'multi Err(e);
                     }
+                });
+                if let Err(e) = $crate::__chain_check(&v[..n], offset, salt) {
+                    break 'multi Err(e);
                 }
             });
-
-            if check != offset {
-                the_end();
-            }
+            Ok(())
         }
-    }
+    };
 }
 
-#[cfg(feature = "obfuscate")]
-#[inline(always)]
-fn the_end() {
-    // Be carefull, optimizer in release mode can decide to remove the following code.
-    // let p: *mut u32 = core::ptr::null_mut();
-    // unsafe {
-    //     *p = 0xFF;
-    // }
-
-    let _res =
-        unsafe { crate::arch::syscall1(crate::arch::SysNo::SYS_EXIT_GROUP, goldberg_int!(0usize)) };
+/// Call `ptrace(PTRACE_TRACEME, ...)` multiple times in nested loops of configurable shape.
+///
+/// This is a wrapper around [`multi_ptraceme_checked!`](macro@crate::multi_ptraceme_checked),
+/// which accepts the same arguments: if any of the checks fails, the macro reacts according to the
+/// configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::multi_ptraceme_or_die!(outer = 4..32, inner = 1..8);
+///
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::multi_ptraceme_or_die!(small);
+/// ```
+#[macro_export]
+macro_rules! multi_ptraceme_or_die {
+    () => {
+        $crate::multi_ptraceme_or_die()
+    };
+    (small) => {
+        $crate::multi_ptraceme_or_die!(outer = 2..5, inner = 1..3)
+    };
+    (outer = $omin:tt..$omax:tt, inner = $imin:tt..$imax:tt $(,)?) => {
+        $crate::__multi_ptraceme_react($crate::multi_ptraceme_checked!(
+            outer = $omin..$omax,
+            inner = $imin..$imax
+        ))
+    };
 }
-#[cfg(not(feature = "obfuscate"))]
-#[inline(always)]
-fn the_end() {
-    // Be carefull, optimizer in release mode can decide to remove the following code.
-    // let p: *mut u32 = core::ptr::null_mut();
-    // unsafe {
-    //     *p = 0xFF;
-    // }
 
-    let _res = unsafe { crate::arch::syscall1(crate::arch::SysNo::SYS_EXIT_GROUP, 0) };
+/// Return a random value drawn from the generator of the current thread.
+#[inline(always)]
+pub(crate) fn rand() -> u32 {
+    with_aa(|aa| aa.r.rand())
 }
 
+/// xoshiro128** generator.
 #[derive(Debug)]
 pub struct Rand {
-    x: u32,
-    y: u32,
-    z: u32,
-    w: u32,
+    s: [u32; 4],
 }
 
 impl Rand {
     #[cfg(feature = "obfuscate")]
-    fn new(seed: u32) -> Rand {
+    fn new(seed: [u32; 4]) -> Rand {
         goldberg_stmts! {
-            let kx: u32 = 123456789u32;
-            let ky: u32 = 362436069u32;
-            let kz: u32 = 521288629u32;
-            let kw: u32 = 88675123u32;
-            Rand{
-                x: kx^seed, y: ky^seed,
-                z: kz, w: kw
+            let mut s = seed;
+            // The all-zero state is a fixed point of the generator.
+            if s == [0u32; 4] {
+                s[0] = const_random!(u32) | 1u32;
             }
+            Rand { s: s }
         }
     }
     #[cfg(not(feature = "obfuscate"))]
-    fn new(seed: u32) -> Rand {
-        let kx: u32 = 123456789u32;
-        let ky: u32 = 362436069u32;
-        let kz: u32 = 521288629u32;
-        let kw: u32 = 88675123u32;
-        Rand {
-            x: kx ^ seed,
-            y: ky ^ seed,
-            z: kz,
-            w: kw,
+    fn new(seed: [u32; 4]) -> Rand {
+        let mut s: [u32; 4] = seed;
+        // The all-zero state is a fixed point of the generator.
+        if s == [0u32; 4] {
+            s[0] = const_random!(u32) | 1u32;
         }
+        Rand { s }
     }
 
     #[cfg(feature = "obfuscate")]
     fn rand(&mut self) -> u32 {
         goldberg_stmts! {
-            let t = self.x^self.x.wrapping_shl(11);
-            self.x = self.y; self.y = self.z; self.z = self.w;
-            self.w ^= self.w.wrapping_shr(19)^t^t.wrapping_shr(8);
-            return self.w;
+            let r = self.s[1].wrapping_mul(5u32).rotate_left(7u32).wrapping_mul(9u32);
+            let t = self.s[1].wrapping_shl(9u32);
+            self.s[2] ^= self.s[0]; self.s[3] ^= self.s[1];
+            self.s[1] ^= self.s[2]; self.s[0] ^= self.s[3];
+            self.s[2] ^= t;
+            self.s[3] = self.s[3].rotate_left(11u32);
+            r
         }
     }
     #[cfg(not(feature = "obfuscate"))]
     fn rand(&mut self) -> u32 {
-        let t = self.x ^ self.x.wrapping_shl(11);
-        self.x = self.y;
-        self.y = self.z;
-        self.z = self.w;
-        self.w ^= self.w.wrapping_shr(19) ^ t ^ t.wrapping_shr(8);
-        self.w
+        let r = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1].wrapping_shl(9);
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(11);
+        r
     }
 }
 
 #[cfg(target_os = "linux")]
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
 
+    use std::sync::Mutex;
+    #[cfg(feature = "std")]
     use std::thread;
+    #[cfg(feature = "std")]
     use std::time::Duration;
 
+    // `ptrace(PTRACE_TRACEME, ...)` makes the parent the tracer of the calling thread. Running the
+    // checks directly in the test binary would make cargo the tracer, which never reaps the
+    // traced threads. Each test therefore runs in a forked child that is reaped here.
+    static FORK_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Run `f` in a forked child and return its exit status (0 when `f` returns `true`).
+    pub(crate) fn run_in_child(f: fn() -> bool) -> i32 {
+        let _guard = FORK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        fork_and_wait(f)
+    }
+
+    /// Same as `run_in_child` but without serialization (for nested forks in a child).
+    fn fork_and_wait(f: fn() -> bool) -> i32 {
+        unsafe {
+            let child = libc::fork();
+            assert!(child >= 0);
+            if child == 0 {
+                let ok = f();
+                libc::_exit(if ok { 0 } else { 1 });
+            }
+
+            loop {
+                let mut status: libc::c_int = 0;
+                let pid = libc::waitpid(-1, &mut status, libc::__WALL);
+                assert!(pid > 0);
+                if libc::WIFSTOPPED(status) {
+                    // Forward the signal to the traced thread and keep it running.
+                    let sig = libc::WSTOPSIG(status);
+                    let sig = if sig == libc::SIGTRAP { 0 } else { sig };
+                    libc::ptrace(libc::PTRACE_CONT, pid, 0, sig);
+                    continue;
+                }
+                if pid == child {
+                    assert!(libc::WIFEXITED(status));
+                    return libc::WEXITSTATUS(status);
+                }
+            }
+        }
+    }
+
+    /// Trace the current thread without updating its state.
+    pub(crate) fn trace_current_thread() {
+        assert!(super::ptraceme().is_ok());
+    }
+
     #[test]
     fn multiple_ptraceme_or_die() {
-        for i in 0..10 {
-            super::Aa::ptraceme_or_die();
-            println!("{}", i);
-        }
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                for i in 0..10 {
+                    super::ptraceme_or_die();
+                    println!("{}", i);
+                }
 
-        super::AA.with(|f| {
-            assert_eq!(10, f.borrow().ptrace_state.traceme_ctr);
-        });
+                super::with_aa(|aa| aa.ptrace_state.traceme_ctr) == 10
+            })
+        );
     }
 
+    // Without the standard library the counter is shared by all the threads.
+    #[cfg(feature = "std")]
     #[test]
     fn multiple_threads_ptraceme_or_die() {
-        let tmp = super::AA.with(|f| f.borrow().ptrace_state.traceme_ctr);
-
-        let threads: Vec<_> = (0..10)
-            .map(|i| {
-                thread::spawn(move || {
-                    super::Aa::ptraceme_or_die();
-                    thread::sleep(Duration::from_millis(i * 10));
-                    println!("Thread #{}", i);
-                    super::Aa::ptraceme_or_die();
-                    super::AA.with(|f| {
-                        assert_eq!(2, f.borrow().ptrace_state.traceme_ctr);
-                    });
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let tmp = super::with_aa(|aa| aa.ptrace_state.traceme_ctr);
+
+                let threads: Vec<_> = (0..10)
+                    .map(|i| {
+                        thread::spawn(move || {
+                            super::ptraceme_or_die();
+                            thread::sleep(Duration::from_millis(i * 10));
+                            println!("Thread #{}", i);
+                            super::ptraceme_or_die();
+                            super::with_aa(|aa| aa.ptrace_state.traceme_ctr) == 2
+                        })
+                    })
+                    .collect();
+
+                let mut ok = true;
+                for thread in threads.into_iter() {
+                    ok &= thread.join().unwrap();
+                }
+
+                println!("END");
+                ok && tmp == super::with_aa(|aa| aa.ptrace_state.traceme_ctr)
+            })
+        );
+    }
+
+    // Without the standard library the exit of a thread is not observed.
+    #[cfg(feature = "std")]
+    #[test]
+    fn global_state() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                super::enable_global_state();
+                let first = super::ptraceme_checked().is_ok();
+                let registered = super::lock_traced_tids().contains(&super::gettid());
+
+                // Lose the thread-local state: the global state must be used instead.
+                super::with_aa(|aa| aa.ptrace_state.traceme_done = false);
+                let second =
+                    super::ptraceme_checked().is_ok() && super::multi_ptraceme_checked().is_ok();
+
+                // The TID of a thread is removed from the global state when the thread exits.
+                let tid = thread::spawn(|| {
+                    super::ptraceme_checked().unwrap();
+                    super::gettid()
                 })
+                .join()
+                .unwrap();
+                let removed = !super::lock_traced_tids().contains(&tid);
+
+                first && registered && second && removed
             })
-            .collect();
+        );
+    }
 
-        for thread in threads.into_iter() {
-            thread.join().unwrap();
+    #[test]
+    fn fork() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let parent = super::ptraceme_checked().is_ok();
+                // The child inherits the state of the parent but it is not traced yet.
+                let child = fork_and_wait(|| {
+                    super::ptraceme_checked().is_ok()
+                        && super::multi_ptraceme_checked().is_ok()
+                        // Double fork, as done by daemons.
+                        && fork_and_wait(|| super::multi_ptraceme_checked().is_ok()) == 0
+                });
+                parent && child == 0 && super::ptraceme_checked().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn fork_global_state() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                super::enable_global_state();
+                let parent = super::ptraceme_checked().is_ok();
+                let child = fork_and_wait(|| {
+                    // Lose the thread-local state: the TIDs of the parent must not be used.
+                    super::with_aa(|aa| aa.ptrace_state.traceme_done = false);
+                    super::ptraceme_checked().is_ok()
+                        && super::lock_traced_tids().contains(&super::gettid())
+                        && super::lock_traced_tids().len() == 1
+                });
+                parent && child == 0 && super::ptraceme_checked().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn ptraceme_checked() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                super::ptraceme_checked().is_ok()
+                    && super::ptraceme_checked().is_ok()
+                    && super::multi_ptraceme_checked().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn rand() {
+        // Reference output of xoshiro128**.
+        let mut r = super::Rand::new([1, 2, 3, 4]);
+        assert_eq!(
+            [11520, 0, 5927040, 70819200, 2031721883],
+            [(); 5].map(|_| r.rand())
+        );
+
+        let mut r = super::Rand::new([0; 4]);
+        assert!((0..4).any(|_| r.rand() != 0));
+    }
+
+    #[test]
+    fn seed() {
+        let (a, b) = (super::seed(), super::seed());
+        assert!(a != [0; 4] && a != b);
+    }
+
+    #[test]
+    fn at_random() {
+        let addr = unsafe { libc::getauxval(libc::AT_RANDOM) } as usize;
+        assert_eq!(Some(addr), super::at_random());
+
+        // Same value as in procfs, read on kernels without `PR_GET_AUXV`.
+        let mut auxv = [0u8; super::AUXV_SIZE];
+        let len = crate::procfs::read_file(b"/proc/self/auxv\0", &mut auxv).unwrap();
+        assert_eq!(
+            Some(addr),
+            super::auxv_value(&auxv[..len], super::AT_RANDOM)
+        );
+        assert_eq!(None, super::auxv_value(&auxv[..len], usize::MAX));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn tid_set() {
+        let mut tids = super::TidSet::new();
+        for tid in 0..super::MAX_TRACED_TIDS as u32 {
+            assert_eq!(Ok(true), tids.try_insert(tid));
         }
+        assert_eq!(
+            Err(crate::DebugOffError::ThreadLimit),
+            tids.try_insert(1000)
+        );
+        assert_eq!(Ok(false), tids.try_insert(3));
+        assert!(tids.remove(&3) && !tids.contains(&3));
+        assert!(tids.try_insert(1000) == Ok(true) && tids.contains(&1000));
+        assert_eq!(super::MAX_TRACED_TIDS, tids.len());
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn tid_set_full() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                {
+                    let mut tids = super::lock_traced_tids();
+                    for tid in 0..super::MAX_TRACED_TIDS as u32 {
+                        let _ = tids.try_insert(u32::MAX - tid);
+                    }
+                }
+                // `PTRACE_TRACEME` completes but cannot be recorded: the failure is reported.
+                crate::ptraceme_checked() == Err(crate::DebugOffError::ThreadLimit)
+            })
+        );
+    }
+
+    #[test]
+    fn multi_ptraceme_shape() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::multi_ptraceme_checked!(outer = 4..32, inner = 1..8).is_ok()
+                    && crate::multi_ptraceme_checked!(small).is_ok()
+                    && crate::multi_ptraceme_checked!().is_ok()
+                    && super::with_aa(|aa| aa.ptrace_state.traceme_ctr) >= 4 + 2 + 16
+            })
+        );
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                // 3 chains of 2 calls.
+                crate::multi_ptraceme_checked!(outer = 3..4, inner = 2..3).is_ok()
+                    && super::with_aa(|aa| aa.ptrace_state.traceme_ctr) == 6
+            })
+        );
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::set_response_policy(crate::ResponsePolicy::Poison).unwrap();
+                crate::multi_ptraceme_or_die!(outer = 1..2, inner = 3..4);
+                let before = crate::is_poisoned();
+                trace_current_thread();
+                crate::multi_ptraceme_or_die!(small);
+                !before && crate::is_poisoned()
+            })
+        );
+    }
 
-        super::AA.with(|f| {
-            assert_eq!(tmp, f.borrow().ptrace_state.traceme_ctr);
-        });
+    #[test]
+    fn pick() {
+        assert_eq!(4, super::__pick(4..5, usize::MAX));
+        assert_eq!(9, super::__pick(4..32, 5));
+        assert!((4..32).contains(&super::__pick(4..32, usize::MAX)));
+    }
 
-        println!("END");
-        std::process::exit(0);
+    #[test]
+    fn ptraceme_checked_already_traced() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                // Trace the thread behind the back of the thread-local state: the following checks
+                // must report it.
+                super::ptraceme().is_ok()
+                    && super::ptraceme_checked() == Err(crate::DebugOffError::AlreadyTraced)
+                    && super::multi_ptraceme_checked() == Err(crate::DebugOffError::AlreadyTraced)
+            })
+        );
     }
 }
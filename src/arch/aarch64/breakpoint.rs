@@ -0,0 +1,16 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::breakpoint::Pattern;
+
+/// Software breakpoint instructions: `brk #imm`.
+pub const BREAKPOINTS: &[Pattern] = &[Pattern {
+    len: 4,
+    align: 4,
+    mask: 0xFFE0_001F,
+    value: 0xD420_0000,
+}];
@@ -0,0 +1,24 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use core::arch::asm;
+
+/// Read the virtual count register of the generic timer.
+#[inline(always)]
+pub fn read_counter() -> u64 {
+    let cnt: u64;
+    unsafe {
+        asm!(
+            // Do not let the read be executed ahead of the preceding instructions.
+            "isb",
+            "mrs {cnt}, cntvct_el0",
+            cnt = out(reg) cnt,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    cnt
+}
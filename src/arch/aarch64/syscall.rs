@@ -19,10 +19,30 @@
 //
 // No other registers are clobbered.
 use super::syscalls::SysNo;
+use crate::errno::{decode, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
+/// Issues a raw system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "svc 0",
+        in("x8") n as usize,
+        lateout("x0") ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
 /// Issues a raw system call with 1 arguments.
 ///
 /// # Safety
@@ -31,15 +51,56 @@ use core::arch::asm;
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "svc 0",
+        in("x8") n as usize,
+        inlateout("x0") arg1 => ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "svc 0",
+        in("x8") n as usize,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "svc 0",
         in("x8") n as usize,
         inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
 /// Issues a raw system call with 4 arguments.
@@ -50,7 +111,13 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "svc 0",
@@ -61,40 +128,57 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("x3") arg4,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
-/// Issues a raw obfuscated system call with 1 arguments.
+/// Issues a raw system call with 5 arguments.
 ///
 /// # Safety
 ///
 /// Running a system call is inherently unsafe. It is the caller's
 /// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
         "svc 0",
         in("x8") n as usize,
         inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
+        in("x3") arg4,
+        in("x4") arg5,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
-/// Issues a raw obfuscate dsystem call with 4 arguments.
+/// Issues a raw system call with 6 arguments.
 ///
 /// # Safety
 ///
 /// Running a system call is inherently unsafe. It is the caller's
 /// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
         "svc 0",
         in("x8") n as usize,
@@ -102,7 +186,248 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("x1") arg2,
         in("x2") arg3,
         in("x3") arg4,
+        in("x4") arg5,
+        in("x5") arg6,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        lateout("x0") ret,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 1 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 4 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
+        in("x3") arg4,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
+        in("x3") arg4,
+        in("x4") arg5,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor x8, x8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add x8, x8, {k}",
+        "2:",
+        "sub x8, x8, #1",
+        "subs {k}, {k}, #1",
+        "b.ne 2b",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("x8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("x0") arg1 => ret,
+        in("x1") arg2,
+        in("x2") arg3,
+        in("x3") arg4,
+        in("x4") arg5,
+        in("x5") arg6,
+        options(nostack)
+    );
+    decode(ret)
 }
@@ -0,0 +1,44 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::breakpoint::Pattern;
+
+/// Software breakpoint instructions: `bkpt #imm` and the undefined instruction used by gdb and
+/// by the kernel (uprobes) as breakpoint.
+#[cfg(not(target_feature = "thumb-mode"))]
+pub const BREAKPOINTS: &[Pattern] = &[
+    Pattern {
+        len: 4,
+        align: 4,
+        mask: 0x0FF0_00F0,
+        value: 0x0120_0070,
+    },
+    Pattern {
+        len: 4,
+        align: 4,
+        mask: 0xFFFF_FFFF,
+        value: 0xE7F0_01F0,
+    },
+];
+
+/// Software breakpoint instructions: `bkpt #imm` and the undefined instruction used by gdb as
+/// Thumb breakpoint.
+#[cfg(target_feature = "thumb-mode")]
+pub const BREAKPOINTS: &[Pattern] = &[
+    Pattern {
+        len: 2,
+        align: 2,
+        mask: 0xFF00,
+        value: 0xBE00,
+    },
+    Pattern {
+        len: 2,
+        align: 2,
+        mask: 0xFFFF,
+        value: 0xDE01,
+    },
+];
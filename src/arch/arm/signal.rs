@@ -0,0 +1,92 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use core::arch::asm;
+
+pub const SIGTRAP: usize = 5;
+pub const SIGCHLD: usize = 17;
+pub const SIGSYS: usize = 31;
+pub const NSIG: usize = 64;
+pub const SA_SIGINFO: usize = 4;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+// The kernel signal set has 64 bits.
+const SIGSET_WORDS: usize = 2;
+
+/// Kernel signal set.
+pub type SigSet = [usize; SIGSET_WORDS];
+
+/// `struct sigaction` as expected by the `rt_sigaction` system call.
+#[repr(C)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub restorer: usize,
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    /// Action running `handler` with `SA_SIGINFO`.
+    pub fn new(handler: usize) -> SigAction {
+        SigAction {
+            handler,
+            flags: SA_SIGINFO,
+            restorer: 0,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+
+    /// Empty action, filled by `rt_sigaction`.
+    pub fn empty() -> SigAction {
+        SigAction {
+            handler: 0,
+            flags: 0,
+            restorer: 0,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+}
+
+/// Execute a breakpoint instruction (the undefined instruction used by gdb, which the kernel reports
+/// as `SIGTRAP`).
+#[cfg(not(target_feature = "thumb-mode"))]
+#[inline(always)]
+pub fn trap() {
+    unsafe {
+        asm!(".inst 0xe7f001f0", options(nostack));
+    }
+}
+
+/// Execute a breakpoint instruction (the undefined instruction used by gdb, which the kernel reports
+/// as `SIGTRAP`).
+#[cfg(target_feature = "thumb-mode")]
+#[inline(always)]
+pub fn trap() {
+    unsafe {
+        asm!(".inst.n 0xde01", options(nostack));
+    }
+}
+
+// Length of the instruction executed by `trap`.
+#[cfg(not(target_feature = "thumb-mode"))]
+const TRAP_LEN: u32 = 4;
+#[cfg(target_feature = "thumb-mode")]
+const TRAP_LEN: u32 = 2;
+
+/// Move the program counter saved in the `ucontext_t` of a signal handler past the instruction
+/// executed by [`trap`] (the kernel reports it with the program counter on the instruction).
+///
+/// # Safety
+///
+/// `uc` must be the `ucontext_t` passed to a `SA_SIGINFO` handler of the signal raised by [`trap`].
+#[inline(always)]
+pub unsafe fn skip_trap(uc: *mut u8) {
+    // Offset of the program counter in `ucontext_t`.
+    let pc = uc.add(92) as *mut u32;
+    *pc = (*pc).wrapping_add(TRAP_LEN);
+}
@@ -19,19 +19,48 @@
 //
 // No other registers are clobbered.
 use super::syscalls::SysNo;
+use crate::errno::{decode, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
-/// Issues a raw system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        // related to https://github.com/rust-lang/rust/issues/85056
+        // "svc 0",
+        // in("r7") n as usize,
+        "mov r7, r8",
+        "svc 0",
+        in("r8") n as usize,
+        lateout("r0") ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        // related to https://github.com/rust-lang/rust/issues/85056
+        // "svc 0",
+        // in("r7") n as usize,
+        "mov r7, r8",
+        "svc 0",
+        in("r8") n as usize,
+        inlateout("r0") arg1 => ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
     let mut ret: usize;
     asm!(
         // related to https://github.com/rust-lang/rust/issues/85056
@@ -41,20 +70,40 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         "svc 0",
         in("r8") n as usize,
         inlateout("r0") arg1 => ret,
+        in("r1") arg2,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
-/// Issues a raw system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        // related to https://github.com/rust-lang/rust/issues/85056
+        // "svc 0",
+        // in("r7") n as usize,
+        "mov r7, r8",
+        "svc 0",
+        in("r8") n as usize,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
     asm!(
         // related to https://github.com/rust-lang/rust/issues/85056
@@ -69,20 +118,20 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("r3") arg4,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
-/// Issues a raw obfuscated system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
         // related to https://github.com/rust-lang/rust/issues/85056
         // "svc 0",
@@ -91,22 +140,27 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         "svc 0",
         in("r8") n as usize,
         inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        in("r3") arg4,
+        in("r4") arg5,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
-/// Issues a raw obfuscated system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
         // related to https://github.com/rust-lang/rust/issues/85056
         // "svc 0",
@@ -118,7 +172,220 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("r1") arg2,
         in("r2") arg3,
         in("r3") arg4,
+        in("r4") arg5,
+        in("r5") arg6,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        lateout("r0") ret,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        in("r3") arg4,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        in("r3") arg4,
+        in("r4") arg5,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "eor r8, r8, {k}",
+        "and {k}, {k}, #0xFF",
+        "add {k}, {k}, #1",
+        "add r8, r8, {k}",
+        "2:",
+        "sub r8, r8, #1",
+        "subs {k}, {k}, #1",
+        "bne 2b",
+        // related to https://github.com/rust-lang/rust/issues/85056
+        "mov r7, r8",
+        "svc 0",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("r8") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("r0") arg1 => ret,
+        in("r1") arg2,
+        in("r2") arg3,
+        in("r3") arg4,
+        in("r4") arg5,
+        in("r5") arg6,
+        options(nostack)
+    );
+    decode(ret)
 }
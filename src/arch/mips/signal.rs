@@ -0,0 +1,71 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use core::arch::asm;
+
+pub const SIGTRAP: usize = 5;
+pub const SIGCHLD: usize = 18;
+pub const SIGSYS: usize = 12;
+pub const NSIG: usize = 128;
+pub const SA_SIGINFO: usize = 8;
+pub const SIG_UNBLOCK: usize = 2;
+pub const SIG_SETMASK: usize = 3;
+
+// The kernel signal set has 128 bits.
+const SIGSET_WORDS: usize = 4;
+
+/// Kernel signal set.
+pub type SigSet = [usize; SIGSET_WORDS];
+
+/// `struct sigaction` as expected by the `rt_sigaction` system call.
+#[repr(C)]
+pub struct SigAction {
+    pub flags: u32,
+    pub handler: usize,
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    /// Action running `handler` with `SA_SIGINFO`.
+    pub fn new(handler: usize) -> SigAction {
+        SigAction {
+            flags: SA_SIGINFO as u32,
+            handler,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+
+    /// Empty action, filled by `rt_sigaction`.
+    pub fn empty() -> SigAction {
+        SigAction {
+            flags: 0,
+            handler: 0,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+}
+
+/// Execute a breakpoint instruction (`break`), which raises `SIGTRAP`.
+#[inline(always)]
+pub fn trap() {
+    unsafe {
+        asm!("break", options(nostack));
+    }
+}
+
+/// Move the program counter saved in the `ucontext_t` of a signal handler past the instruction
+/// executed by [`trap`] (the kernel reports `break` with the program counter on the instruction).
+///
+/// # Safety
+///
+/// `uc` must be the `ucontext_t` passed to a `SA_SIGINFO` handler of the signal raised by [`trap`].
+#[inline(always)]
+pub unsafe fn skip_trap(uc: *mut u8) {
+    // Offset of the program counter in `ucontext_t`.
+    let pc = uc.add(32) as *mut u64;
+    *pc = (*pc).wrapping_add(4);
+}
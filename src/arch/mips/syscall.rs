@@ -38,19 +38,104 @@
 //
 // All temporary registers are clobbered (8-15, 24-25).
 use super::syscalls::SysNo;
+use crate::errno::{Errno, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
-/// Issues a raw system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
     asm!(
@@ -58,6 +143,8 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         inlateout("$2") n as usize => ret,
         lateout("$7") err,
         in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
         // All temporary registers are always clobbered
         lateout("$8") _,
         lateout("$9") _,
@@ -72,21 +159,21 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
     asm!(
@@ -111,27 +198,166 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw obfuscated system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        // Arguments 5 and 6 are passed on the stack, after the 16 bytes
+        // reserved for the first four arguments.
+        "subu $sp, 32",
+        "sw {arg5}, 16($sp)",
+        "syscall",
+        "addu $sp, 32",
+        arg5 = in(reg) arg5,
+        inlateout("$2") n as usize => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
+        // Arguments 5 and 6 are passed on the stack, after the 16 bytes
+        // reserved for the first four arguments.
+        "subu $sp, 32",
+        "sw {arg5}, 16($sp)",
+        "sw {arg6}, 20($sp)",
         "syscall",
+        "addu $sp, 32",
+        arg5 = in(reg) arg5,
+        arg6 = in(reg) arg6,
         inlateout("$2") n as usize => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
         lateout("$7") err,
         in("$4") arg1,
         // All temporary registers are always clobbered
@@ -148,27 +374,122 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw obfuscated system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
+    let key: usize = const_random!(usize);
     asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
         "syscall",
-        inlateout("$2") n as usize => ret,
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
         in("$4") arg1,
         in("$5") arg2,
         in("$6") arg3,
@@ -188,8 +509,125 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        // Arguments 5 and 6 are passed on the stack, after the 16 bytes
+        // reserved for the first four arguments.
+        "subu $sp, 32",
+        "sw {arg5}, 16($sp)",
+        "syscall",
+        "addu $sp, 32",
+        k = inout(reg) ((key as u16) as usize) => _,
+        arg5 = in(reg) arg5,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addiu {k}, {k}, 1",
+        "addu $2, $2, {k}",
+        "2:",
+        "addiu $2, $2, -1",
+        "addiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        // Arguments 5 and 6 are passed on the stack, after the 16 bytes
+        // reserved for the first four arguments.
+        "subu $sp, 32",
+        "sw {arg5}, 16($sp)",
+        "sw {arg6}, 20($sp)",
+        "syscall",
+        "addu $sp, 32",
+        k = inout(reg) ((key as u16) as usize) => _,
+        arg5 = in(reg) arg5,
+        arg6 = in(reg) arg6,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
@@ -0,0 +1,16 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::breakpoint::Pattern;
+
+/// Software breakpoint instructions: `break code`.
+pub const BREAKPOINTS: &[Pattern] = &[Pattern {
+    len: 4,
+    align: 4,
+    mask: 0xFC00_003F,
+    value: 0x0000_000D,
+}];
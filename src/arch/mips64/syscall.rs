@@ -40,19 +40,73 @@
 // the stack to pass in args 5-6. Instead, it uses the temporary registers t0
 // and t1, which still get clobbered.
 use super::syscalls::SysNo;
+use crate::errno::{Errno, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
-/// Issues a raw system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
     asm!(
@@ -60,6 +114,7 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         inlateout("$2") n as usize => ret,
         lateout("$7") err,
         in("$4") arg1,
+        in("$5") arg2,
         // All temporary registers are always clobbered
         lateout("$8") _,
         lateout("$9") _,
@@ -74,21 +129,53 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
     asm!(
@@ -113,27 +200,152 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw obfuscated system call with 1 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
-#[cfg(feature = "syscallobf")]
+#[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
     asm!(
         "syscall",
         inlateout("$2") n as usize => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        inlateout("$8") arg5 => _,
+        // All temporary registers are always clobbered
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("$2") n as usize => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        inlateout("$8") arg5 => _,
+        inlateout("$9") arg6 => _,
+        // All temporary registers are always clobbered
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
         lateout("$7") err,
         in("$4") arg1,
         // All temporary registers are always clobbered
@@ -150,27 +362,122 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
 
-/// Issues a raw obfuscated system call with 4 arguments.
-///
-/// # Safety
-///
-/// Running a system call is inherently unsafe. It is the caller's
-/// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
     let mut err: usize;
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
+    let key: usize = const_random!(usize);
     asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
         "syscall",
-        inlateout("$2") n as usize => ret,
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        lateout("$7") err,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // All temporary registers are always clobbered
+        lateout("$8") _,
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
         in("$4") arg1,
         in("$5") arg2,
         in("$6") arg3,
@@ -190,8 +497,111 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         options(nostack, preserves_flags)
     );
     if err == 0 {
-        ret
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        inlateout("$8") arg5 => _,
+        // All temporary registers are always clobbered
+        lateout("$9") _,
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
+    } else {
+        Err(Errno::new(ret as i32))
+    }
+}
+
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut err: usize;
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor $2, $2, {k}",
+        "andi {k}, {k}, 0xFF",
+        "daddiu {k}, {k}, 1",
+        "daddu $2, $2, {k}",
+        "2:",
+        "daddiu $2, $2, -1",
+        "daddiu {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "nop",
+        "syscall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inlateout("$2") ((key as u16) as usize) ^ (n as usize) => ret,
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        // $7 is now used for both input and output.
+        inlateout("$7") arg4 => err,
+        inlateout("$8") arg5 => _,
+        inlateout("$9") arg6 => _,
+        // All temporary registers are always clobbered
+        lateout("$10") _,
+        lateout("$11") _,
+        lateout("$12") _,
+        lateout("$13") _,
+        lateout("$14") _,
+        lateout("$15") _,
+        lateout("$24") _,
+        lateout("$25") _,
+        options(nostack, preserves_flags)
+    );
+    if err == 0 {
+        Ok(ret)
     } else {
-        ret.wrapping_neg()
+        Err(Errno::new(ret as i32))
     }
 }
@@ -39,3 +39,48 @@ pub use aarch64::*;
 mod riscv64;
 #[cfg(target_arch = "riscv64")]
 pub use riscv64::*;
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn syscall_arities() {
+        unsafe {
+            let pid = syscall0(SysNo::SYS_GETPID).unwrap();
+            assert_eq!(std::process::id() as usize, pid);
+
+            // kill(pid, 0) only checks that the process exists.
+            assert_eq!(Ok(0), syscall2(SysNo::SYS_KILL, pid, 0));
+
+            assert_eq!(
+                Ok(1),
+                syscall5(SysNo::SYS_PRCTL, libc::PR_GET_DUMPABLE as usize, 0, 0, 0, 0)
+            );
+
+            #[cfg(target_pointer_width = "64")]
+            let mmap = SysNo::SYS_MMAP;
+            #[cfg(target_pointer_width = "32")]
+            let mmap = SysNo::SYS_MMAP2;
+            let page = syscall6(
+                mmap,
+                0,
+                4096,
+                (libc::PROT_READ | libc::PROT_WRITE) as usize,
+                (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as usize,
+                -1isize as usize,
+                0,
+            )
+            .unwrap();
+            *(page as *mut u8) = 0xAA;
+
+            assert_eq!(
+                Ok(0),
+                syscall3(SysNo::SYS_MPROTECT, page, 4096, libc::PROT_READ as usize)
+            );
+            assert_eq!(Ok(0), syscall2(SysNo::SYS_MUNMAP, page, 4096));
+        }
+    }
+}
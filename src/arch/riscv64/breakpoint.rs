@@ -0,0 +1,25 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::breakpoint::Pattern;
+
+/// Software breakpoint instructions: `ebreak` and `c.ebreak` (instructions are 2-byte aligned
+/// when the compressed extension is used).
+pub const BREAKPOINTS: &[Pattern] = &[
+    Pattern {
+        len: 4,
+        align: 2,
+        mask: 0xFFFF_FFFF,
+        value: 0x0010_0073,
+    },
+    Pattern {
+        len: 2,
+        align: 2,
+        mask: 0xFFFF,
+        value: 0x9002,
+    },
+];
@@ -0,0 +1,22 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use core::arch::asm;
+
+/// Read the `time` CSR.
+#[inline(always)]
+pub fn read_counter() -> u64 {
+    let time: u64;
+    unsafe {
+        asm!(
+            "rdtime {time}",
+            time = out(reg) time,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    time
+}
@@ -5,11 +5,13 @@
 // - GPL-3.0 when "obfuscate" feature is enabled;
 // - MIT when "obfuscate" feature IS NOT enabled;
 
-// On riscv64, the following registers are used for args 1-4:
+// On riscv64, the following registers are used for args 1-6:
 // arg1: %a0
 // arg2: %a1
 // arg3: %a2
 // arg4: %a3
+// arg5: %a4
+// arg6: %a5
 //
 // %a7 is used for the syscall number.
 //
@@ -17,10 +19,30 @@
 //
 // No other registers are clobbered.
 use super::syscalls::SysNo;
+use crate::errno::{decode, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
+/// Issues a raw system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "ecall",
+        in("a7") n as usize,
+        lateout("a0") ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
 /// Issues a raw system call with 1 arguments.
 ///
 /// # Safety
@@ -29,15 +51,56 @@ use core::arch::asm;
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "ecall",
+        in("a7") n as usize,
+        inlateout("a0") arg1 => ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "ecall",
+        in("a7") n as usize,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
     let mut ret: usize;
     asm!(
-        "ecall 0",
+        "ecall",
         in("a7") n as usize,
         inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
 /// Issues a raw system call with 4 arguments.
@@ -48,10 +111,16 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
     asm!(
-        "ecall 0",
+        "ecall",
         in("a7") n as usize,
         inlateout("a0") arg1 => ret,
         in("a1") arg2,
@@ -59,7 +128,98 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("a3") arg4,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "ecall",
+        in("a7") n as usize,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
+        in("a3") arg4,
+        in("a4") arg5,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "ecall",
+        in("a7") n as usize,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
+        in("a3") arg4,
+        in("a4") arg5,
+        in("a5") arg6,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
+        lateout("a0") ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 1 arguments.
@@ -70,16 +230,86 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
+    let key: usize = const_random!(usize);
     asm!(
-        "ecall 0",
-        in("a7") n as usize,
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
         inlateout("a0") arg1 => ret,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 4 arguments.
@@ -90,17 +320,114 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
-    let _key: usize = const_random!(usize);
+    let key: usize = const_random!(usize);
     asm!(
-        "ecall 0",
-        in("a7") n as usize,
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
+        in("a3") arg4,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
+        inlateout("a0") arg1 => ret,
+        in("a1") arg2,
+        in("a2") arg3,
+        in("a3") arg4,
+        in("a4") arg5,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor a7, a7, {k}",
+        "andi {k}, {k}, 0xFF",
+        "addi {k}, {k}, 1",
+        "add a7, a7, {k}",
+        "2:",
+        "addi a7, a7, -1",
+        "addi {k}, {k}, -1",
+        "bnez {k}, 2b",
+        "ecall",
+        k = inout(reg) ((key as u16) as usize) => _,
+        inout("a7") ((key as u16) as usize) ^ (n as usize) => _,
         inlateout("a0") arg1 => ret,
         in("a1") arg2,
         in("a2") arg3,
         in("a3") arg4,
+        in("a4") arg5,
+        in("a5") arg6,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
@@ -19,10 +19,29 @@
 // `asm!()` macro, it is assumed that memory is clobbered unless the nomem
 // option is specified.
 use super::syscalls::SysNo;
+use crate::errno::{decode, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
+/// Issues a raw system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
 /// Issues a raw system call with 1 arguments.
 ///
 /// # Safety
@@ -31,15 +50,56 @@ use core::arch::asm;
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "int $$0x80",
         inlateout("eax") n as usize => ret,
         in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
 }
 
 /// Issues a raw system call with 4 arguments.
@@ -50,7 +110,46 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "xchg esi, {arg4}",
+        "int $$0x80",
+        "xchg esi, {arg4}",
+        // Using esi is not allowed, so we need to use another register to
+        // save/restore esi. Thus, we can say that esi is not clobbered.
+        arg4 = in(reg) arg4,
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "xchg esi, {arg4}",
@@ -63,9 +162,70 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("ebx") arg1,
         in("ecx") arg2,
         in("edx") arg3,
+        in("edi") arg5,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    // esi and ebp cannot be used as operands and all the other registers are
+    // already taken by the arguments: arg4, arg6 and the syscall number are
+    // passed through memory, using eax as pointer.
+    let args: [usize; 3] = [arg4, arg6, n as usize];
+    asm!(
+        "push ebp",
+        "push esi",
+        "mov esi, [eax]",
+        "mov ebp, [eax + 4]",
+        "mov eax, [eax + 8]",
+        "int $$0x80",
+        "pop esi",
+        "pop ebp",
+        inlateout("eax") args.as_ptr() => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
+        in("edi") arg5,
+        options(preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    let _key: usize = const_random!(usize);
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 1 arguments.
@@ -76,16 +236,59 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
+    let mut ret: usize;
+    let _key: usize = const_random!(usize);
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
     let mut ret: usize;
     let _key: usize = const_random!(usize);
     asm!(
         "int $$0x80",
         inlateout("eax") n as usize => ret,
         in("ebx") arg1,
+        in("ecx") arg2,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    let _key: usize = const_random!(usize);
+    asm!(
+        "int $$0x80",
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 4 arguments.
@@ -96,7 +299,13 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
     let _key: usize = const_random!(usize);
     asm!(
@@ -112,5 +321,82 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("edx") arg3,
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let _key: usize = const_random!(usize);
+    asm!(
+        "xchg esi, {arg4}",
+        "int $$0x80",
+        "xchg esi, {arg4}",
+        // Using esi is not allowed, so we need to use another register to
+        // save/restore esi. Thus, we can say that esi is not clobbered.
+        arg4 = in(reg) arg4,
+        inlateout("eax") n as usize => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
+        in("edi") arg5,
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let _key: usize = const_random!(usize);
+    // esi and ebp cannot be used as operands and all the other registers are
+    // already taken by the arguments: arg4, arg6 and the syscall number are
+    // passed through memory, using eax as pointer.
+    let args: [usize; 3] = [arg4, arg6, n as usize];
+    asm!(
+        "push ebp",
+        "push esi",
+        "mov esi, [eax]",
+        "mov ebp, [eax + 4]",
+        "mov eax, [eax + 8]",
+        "int $$0x80",
+        "pop esi",
+        "pop ebp",
+        inlateout("eax") args.as_ptr() => ret,
+        in("ebx") arg1,
+        in("ecx") arg2,
+        in("edx") arg3,
+        in("edi") arg5,
+        options(preserves_flags)
+    );
+    decode(ret)
 }
@@ -0,0 +1,16 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::breakpoint::Pattern;
+
+/// Software breakpoint instructions: `int3`.
+pub const BREAKPOINTS: &[Pattern] = &[Pattern {
+    len: 1,
+    align: 1,
+    mask: 0xFF,
+    value: 0xCC,
+}];
@@ -5,8 +5,16 @@
 // - GPL-3.0 when "obfuscate" feature is enabled;
 // - MIT when "obfuscate" feature IS NOT enabled;
 
+#[cfg(feature = "std")]
+mod breakpoint;
+mod counter;
+mod signal;
 mod syscall;
 mod syscalls;
 
+#[cfg(feature = "std")]
+pub use self::breakpoint::*;
+pub use self::counter::*;
+pub use self::signal::*;
 pub use self::syscall::*;
 pub use self::syscalls::*;
@@ -0,0 +1,122 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use super::SysNo;
+use core::arch::{asm, global_asm};
+
+pub const SIGTRAP: usize = 5;
+pub const SIGCHLD: usize = 17;
+pub const SIGSYS: usize = 31;
+pub const NSIG: usize = 64;
+pub const SA_SIGINFO: usize = 4;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+// The kernel signal set has 64 bits.
+const SIGSET_WORDS: usize = 1;
+
+/// Kernel signal set.
+pub type SigSet = [usize; SIGSET_WORDS];
+const SA_RESTORER: usize = 0x0400_0000;
+
+// On x86_64 the handlers must return through a user-provided `rt_sigreturn` trampoline.
+#[cfg(not(feature = "syscallobf"))]
+global_asm!(
+    ".pushsection .text.__debugoff_restore_rt,\"ax\",@progbits",
+    ".globl __debugoff_restore_rt",
+    ".hidden __debugoff_restore_rt",
+    ".p2align 4",
+    "__debugoff_restore_rt:",
+    "mov eax, {nr}",
+    "syscall",
+    ".popsection",
+    nr = const SysNo::SYS_RT_SIGRETURN as usize,
+);
+
+// Key of the obfuscated system call number of the trampoline.
+#[cfg(feature = "syscallobf")]
+const RESTORE_KEY: usize = const_random::const_random!(u16) as usize;
+
+// Same trampoline, with the system call number decoded as in the obfuscated `syscall*` functions
+// (all the registers are restored by `rt_sigreturn`).
+#[cfg(feature = "syscallobf")]
+global_asm!(
+    ".pushsection .text.__debugoff_restore_rt,\"ax\",@progbits",
+    ".globl __debugoff_restore_rt",
+    ".hidden __debugoff_restore_rt",
+    ".p2align 4",
+    "__debugoff_restore_rt:",
+    "mov ecx, {key}",
+    "mov r11d, {enc}",
+    "xor r11, rcx",
+    "mov rax, r11",
+    "and rcx, 0xFF",
+    "add rcx, 1",
+    "add rax, rcx",
+    "2:",
+    "sub rax, 1",
+    "sub rcx, 1",
+    "cmp rcx, 0",
+    "jg 2b",
+    "syscall",
+    ".popsection",
+    key = const RESTORE_KEY,
+    enc = const RESTORE_KEY ^ SysNo::SYS_RT_SIGRETURN as usize,
+);
+
+extern "C" {
+    fn __debugoff_restore_rt();
+}
+
+/// `struct sigaction` as expected by the `rt_sigaction` system call.
+#[repr(C)]
+pub struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub restorer: usize,
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    /// Action running `handler` with `SA_SIGINFO`.
+    pub fn new(handler: usize) -> SigAction {
+        SigAction {
+            handler,
+            flags: SA_SIGINFO | SA_RESTORER,
+            restorer: __debugoff_restore_rt as unsafe extern "C" fn() as usize,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+
+    /// Empty action, filled by `rt_sigaction`.
+    pub fn empty() -> SigAction {
+        SigAction {
+            handler: 0,
+            flags: 0,
+            restorer: 0,
+            mask: [0; SIGSET_WORDS],
+        }
+    }
+}
+
+/// Execute a breakpoint instruction (`int3`), which raises `SIGTRAP`.
+#[inline(always)]
+pub fn trap() {
+    unsafe {
+        asm!("int3", options(nostack));
+    }
+}
+
+/// Move the program counter saved in the `ucontext_t` of a signal handler past the instruction
+/// executed by [`trap`]. The kernel reports `int3` with the program counter already past the
+/// instruction: nothing to do.
+///
+/// # Safety
+///
+/// `uc` must be the `ucontext_t` passed to a `SA_SIGINFO` handler of the signal raised by [`trap`].
+#[inline(always)]
+pub unsafe fn skip_trap(_uc: *mut u8) {}
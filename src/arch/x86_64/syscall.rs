@@ -19,10 +19,31 @@
 // `asm!()` macro, it is assumed that memory is clobbered unless the nomem
 // option is specified.
 use super::syscalls::SysNo;
+use crate::errno::{decode, SyscallResult};
 #[cfg(feature = "syscallobf")]
 use const_random::const_random;
 use core::arch::asm;
 
+/// Issues a raw system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as usize => ret,
+        out("rcx") _, // rcx is used to store old rip
+        out("r11") _, // r11 is used to store old rflags
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
 /// Issues a raw system call with 1 arguments.
 ///
 /// # Safety
@@ -31,7 +52,7 @@ use core::arch::asm;
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "syscall",
@@ -41,7 +62,52 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         out("r11") _, // r11 is used to store old rflags
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as usize => ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        out("rcx") _, // rcx is used to store old rip
+        out("r11") _, // r11 is used to store old rflags
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as usize => ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        out("rcx") _, // rcx is used to store old rip
+        out("r11") _, // r11 is used to store old rflags
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
 }
 
 /// Issues a raw system call with 4 arguments.
@@ -52,7 +118,44 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(not(feature = "syscallobf"))]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as usize => ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        in("r10") arg4,
+        out("rcx") _, // rcx is used to store old rip
+        out("r11") _, // r11 is used to store old rflags
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
     let mut ret: usize;
     asm!(
         "syscall",
@@ -61,11 +164,79 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("rsi") arg2,
         in("rdx") arg3,
         in("r10") arg4,
+        in("r8") arg5,
         out("rcx") _, // rcx is used to store old rip
         out("r11") _, // r11 is used to store old rflags
         options(nostack, preserves_flags)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(not(feature = "syscallobf"))]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as usize => ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        in("r10") arg4,
+        in("r8") arg5,
+        in("r9") arg6,
+        out("rcx") _, // rcx is used to store old rip
+        out("r11") _, // r11 is used to store old rflags
+        options(nostack, preserves_flags)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with no arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall0(n: SysNo) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor r11, rcx",
+        "mov rax, r11",
+        "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
+        "add rax, rcx",
+        "2:",
+        "sub rax, 1",
+        "sub rcx, 1",
+        "cmp rcx, 0",
+        "jg 2b",
+        "syscall",
+        inout("rcx") ((key as u16) as usize) => _, // rcx is used to store old rip
+        inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
+        out("rax") ret,
+        options(nostack)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 1 arguments.
@@ -76,13 +247,16 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
+pub unsafe fn syscall1(n: SysNo, arg1: usize) -> SyscallResult {
     let mut ret: usize;
     let key: usize = const_random!(usize);
     asm!(
         "xor r11, rcx",
         "mov rax, r11",
         "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
         "add rax, rcx",
         "2:",
         "sub rax, 1",
@@ -94,9 +268,80 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
         inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
         out("rax") ret,
         in("rdi") arg1,
-        options(nostack, preserves_flags)
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 2 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall2(n: SysNo, arg1: usize, arg2: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor r11, rcx",
+        "mov rax, r11",
+        "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
+        "add rax, rcx",
+        "2:",
+        "sub rax, 1",
+        "sub rcx, 1",
+        "cmp rcx, 0",
+        "jg 2b",
+        "syscall",
+        inout("rcx") ((key as u16) as usize) => _, // rcx is used to store old rip
+        inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
+        out("rax") ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        options(nostack)
     );
-    ret
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 3 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall3(n: SysNo, arg1: usize, arg2: usize, arg3: usize) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor r11, rcx",
+        "mov rax, r11",
+        "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
+        "add rax, rcx",
+        "2:",
+        "sub rax, 1",
+        "sub rcx, 1",
+        "cmp rcx, 0",
+        "jg 2b",
+        "syscall",
+        inout("rcx") ((key as u16) as usize) => _, // rcx is used to store old rip
+        inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
+        out("rax") ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        options(nostack)
+    );
+    decode(ret)
 }
 
 /// Issues a raw obfuscated system call with 4 arguments.
@@ -107,13 +352,22 @@ pub unsafe fn syscall1(n: SysNo, arg1: usize) -> usize {
 /// responsibility to ensure safety.
 #[cfg(feature = "syscallobf")]
 #[inline(always)]
-pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> usize {
+pub unsafe fn syscall4(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> SyscallResult {
     let mut ret: usize;
     let key: usize = const_random!(usize);
     asm!(
         "xor r11, rcx",
         "mov rax, r11",
         "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
         "add rax, rcx",
         "2:",
         "sub rax, 1",
@@ -128,20 +382,99 @@ pub unsafe fn syscall4(n: SysNo, arg1: usize, arg2: usize, arg3: usize, arg4: us
         in("rsi") arg2,
         in("rdx") arg3,
         in("r10") arg4,
-        options(nostack, preserves_flags)
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 5 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall5(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor r11, rcx",
+        "mov rax, r11",
+        "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
+        "add rax, rcx",
+        "2:",
+        "sub rax, 1",
+        "sub rcx, 1",
+        "cmp rcx, 0",
+        "jg 2b",
+        "syscall",
+        inout("rcx") ((key as u16) as usize) => _, // rcx is used to store old rip
+        inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
+        out("rax") ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        in("r10") arg4,
+        in("r8") arg5,
+        options(nostack)
+    );
+    decode(ret)
+}
+
+/// Issues a raw obfuscated system call with 6 arguments.
+///
+/// # Safety
+///
+/// Running a system call is inherently unsafe. It is the caller's
+/// responsibility to ensure safety.
+#[cfg(feature = "syscallobf")]
+#[inline(always)]
+pub unsafe fn syscall6(
+    n: SysNo,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> SyscallResult {
+    let mut ret: usize;
+    let key: usize = const_random!(usize);
+    asm!(
+        "xor r11, rcx",
+        "mov rax, r11",
+        "and rcx, 0xFF",
+        // At least one iteration: the loop must not be skipped when the low
+        // byte of the key is zero.
+        "add rcx, 1",
+        "add rax, rcx",
+        "2:",
+        "sub rax, 1",
+        "sub rcx, 1",
+        "cmp rcx, 0",
+        "jg 2b",
+        "syscall",
+        inout("rcx") ((key as u16) as usize) => _, // rcx is used to store old rip
+        inout("r11") ((key as u16) as usize) ^ (n as usize) => _, // r11 is used to store old rflags
+        out("rax") ret,
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        in("r10") arg4,
+        in("r8") arg5,
+        in("r9") arg6,
+        options(nostack)
     );
-    // asm!(
-    //     "xor rax, {0}",
-    //     "syscall",
-    //     in(reg) ((key as u8) as usize),
-    //     inlateout("rax") (n as usize) ^ ((key as u8) as usize) => ret,
-    //     in("rdi") arg1,
-    //     in("rsi") arg2,
-    //     in("rdx") arg3,
-    //     in("r10") arg4,
-    //     out("rcx") _, // rcx is used to store old rip
-    //     out("r11") _, // r11 is used to store old rflags
-    //     options(nostack, preserves_flags)
-    // );
-    ret
+    decode(ret)
 }
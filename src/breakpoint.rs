@@ -0,0 +1,263 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Software breakpoint scanner.
+//
+// Debuggers set software breakpoints by overwriting an instruction with a trap instruction (e.g.,
+// `int3` on x86). The scanner looks for the trap instructions of the target architecture (see
+// `BREAKPOINTS` in `crate::arch`) in a region of code.
+//
+// The same encodings can legitimately appear in the code (e.g., `0xCC` in an operand of an x86
+// instruction or in the padding between functions), so every match is compared with the content
+// of the file mapped at the same address (found in `/proc/self/maps`): only the bytes which
+// differ from the file are reported. When the file cannot be read, every match is reported.
+
+use crate::arch::BREAKPOINTS;
+use crate::policy::the_end;
+use crate::procfs::{self, RawFile};
+use crate::DebugOffError;
+use core::ptr;
+
+/// Encoding of a software breakpoint instruction.
+pub struct Pattern {
+    /// Length of the instruction in bytes (1, 2 or 4).
+    pub len: usize,
+    /// Alignment of the instruction in bytes.
+    pub align: usize,
+    /// Bits of the instruction (read as a native endian integer) that identify it.
+    pub mask: u32,
+    /// Value of the masked bits.
+    pub value: u32,
+}
+
+impl Pattern {
+    /// Return `true` if the `len` bytes at `p` encode the instruction.
+    ///
+    /// # Safety
+    ///
+    /// `p` must be valid for reads of `self.len` bytes.
+    unsafe fn matches(&self, p: *const u8) -> bool {
+        let mut bytes = [0u8; 4];
+        for (i, b) in bytes[..self.len].iter_mut().enumerate() {
+            *b = ptr::read_volatile(p.add(i));
+        }
+        let word = match self.len {
+            1 => bytes[0] as u32,
+            2 => u16::from_ne_bytes([bytes[0], bytes[1]]) as u32,
+            _ => u32::from_ne_bytes(bytes),
+        };
+        word & self.mask == self.value
+    }
+}
+
+/// File mapped over a part of the scanned region.
+struct DiskImage {
+    /// Mapped address range.
+    start: usize,
+    end: usize,
+    /// Offset in the file of the start of the mapping.
+    offset: usize,
+    file: RawFile,
+}
+
+impl DiskImage {
+    /// Find the file mapped at `addr` in `/proc/self/maps` and open it.
+    fn open(addr: usize) -> Option<DiskImage> {
+        let mut path = [0u8; 256];
+        let mut found = None;
+        let res = procfs::for_each_line(b"/proc/self/maps\0", |line| {
+            let m = procfs::parse_mapping(line).ok_or(DebugOffError::ProcFormat)?;
+            // Anonymous mappings and files removed from the disk cannot be compared.
+            if (m.start..m.end).contains(&addr)
+                && m.path.starts_with(b"/")
+                && !m.path.ends_with(b"(deleted)")
+                && m.path.len() < path.len()
+            {
+                path[..m.path.len()].copy_from_slice(m.path);
+                path[m.path.len()] = 0;
+                found = Some((m.start, m.end, m.offset, m.path.len()));
+            }
+            Ok(())
+        });
+        let (start, end, offset, name_len) = res.ok().and(found)?;
+        let file = RawFile::open(&path[..=name_len]).ok()?;
+        Some(DiskImage {
+            start,
+            end,
+            offset,
+            file,
+        })
+    }
+
+    /// Return `true` if the mapping contains the `len` bytes at `addr`.
+    fn contains(&self, addr: usize, len: usize) -> bool {
+        addr >= self.start && addr + len <= self.end
+    }
+
+    /// Return `true` if the `len` bytes at `addr` are the same in the file, i.e., they are part of
+    /// the original code.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must be valid for reads of `len` bytes (at most 4) and contained in the mapping.
+    unsafe fn unchanged(&self, addr: usize, len: usize) -> bool {
+        let offset = self.offset + addr - self.start;
+        let mut disk = [0u8; 4];
+        if self.file.seek(offset) != Ok(offset) || self.file.read_all(&mut disk[..len]) != Ok(len) {
+            return false;
+        }
+        (0..len).all(|i| ptr::read_volatile((addr as *const u8).add(i)) == disk[i])
+    }
+}
+
+/// Return `true` if the `len` bytes at `addr` are the same in the file mapped at `addr`.
+///
+/// The file is resolved and opened only when `disk` does not already contain `addr`, so that a
+/// scan parses `/proc/self/maps` once for every mapping with a match.
+///
+/// # Safety
+///
+/// `addr` must be valid for reads of `len` bytes (at most 4).
+unsafe fn unchanged_on_disk(disk: &mut Option<DiskImage>, addr: usize, len: usize) -> bool {
+    if !disk.as_ref().is_some_and(|d| d.contains(addr, len)) {
+        *disk = DiskImage::open(addr);
+    }
+    match disk {
+        Some(d) if d.contains(addr, len) => d.unchanged(addr, len),
+        _ => false,
+    }
+}
+
+/// Scan `len` bytes of code starting at `start` for software breakpoints.
+///
+/// Returns [`DebugOffError::Breakpoint`] with the offset of the first breakpoint found from
+/// `start`. Matching instructions which are also found at the same position in the executable
+/// file (or shared library) on disk are part of the original code and are not reported.
+///
+/// The region of a function can be recorded at build time with the `critical` attribute (`macros`
+/// feature), which scans the function every time it is entered.
+///
+/// # Safety
+///
+/// The region must be mapped and readable (e.g., the code of a function).
+///
+/// ## Examples
+///
+/// ```rust
+/// #[inline(never)]
+/// fn critical() -> u32 {
+///     42
+/// }
+///
+/// let res = unsafe { debugoff::scan_breakpoints(critical as *const u8, 1) };
+/// assert!(res.is_ok());
+/// ```
+#[inline(never)]
+pub unsafe fn scan_breakpoints(start: *const u8, len: usize) -> Result<(), DebugOffError> {
+    let mut disk = None;
+    for pattern in BREAKPOINTS {
+        let mut offset = (pattern.align - start as usize % pattern.align) % pattern.align;
+        while offset + pattern.len <= len {
+            let p = start.add(offset);
+            if pattern.matches(p) && !unchanged_on_disk(&mut disk, p as usize, pattern.len) {
+                return Err(DebugOffError::Breakpoint(offset));
+            }
+            offset += pattern.align;
+        }
+    }
+    Ok(())
+}
+
+/// Scan `len` bytes of code starting at `start` for software breakpoints.
+///
+/// This is a wrapper around [`scan_breakpoints`]: if a breakpoint is found, the function reacts
+/// according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+/// `exit_group(0)`).
+///
+/// # Safety
+///
+/// The region must be mapped and readable (e.g., the code of a function).
+#[inline(always)]
+pub unsafe fn scan_breakpoints_or_die(start: *const u8, len: usize) {
+    if scan_breakpoints(start, len).is_err() {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{scan_breakpoints, BREAKPOINTS};
+    use crate::aa::test::run_in_child;
+    use crate::DebugOffError;
+
+    /// Encoding of the first breakpoint instruction of the architecture.
+    fn breakpoint() -> Vec<u8> {
+        let p = &BREAKPOINTS[0];
+        match p.len {
+            1 => vec![p.value as u8],
+            2 => (p.value as u16).to_ne_bytes().to_vec(),
+            _ => p.value.to_ne_bytes().to_vec(),
+        }
+    }
+
+    #[inline(never)]
+    #[link_section = "debugoff_test_sample"]
+    fn sample(v: u32) -> u32 {
+        v.wrapping_mul(0x9E37_79B9).rotate_left(5) ^ 0xCCCC
+    }
+
+    // Keeps the section in release builds, where the calls to `sample` are folded.
+    #[used]
+    static SAMPLE: fn(u32) -> u32 = sample;
+
+    extern "C" {
+        #[link_name = "__start_debugoff_test_sample"]
+        static SAMPLE_START: u8;
+        #[link_name = "__stop_debugoff_test_sample"]
+        static SAMPLE_STOP: u8;
+    }
+
+    #[test]
+    fn buffer() {
+        // Heap memory is not backed by a file: every match is reported.
+        let mut buf = [0u8; 64];
+        assert_eq!(Ok(()), unsafe { scan_breakpoints(buf.as_ptr(), buf.len()) });
+
+        let bp = breakpoint();
+        buf[32..32 + bp.len()].copy_from_slice(&bp);
+        assert_eq!(Err(DebugOffError::Breakpoint(32)), unsafe {
+            scan_breakpoints(buf.as_ptr(), buf.len())
+        });
+        // The breakpoint is outside of the scanned region.
+        assert_eq!(Ok(()), unsafe { scan_breakpoints(buf.as_ptr(), 32) });
+    }
+
+    #[test]
+    fn function() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                let start = core::ptr::addr_of!(SAMPLE_START);
+                let len = core::ptr::addr_of!(SAMPLE_STOP) as usize - start as usize;
+                let clean = scan_breakpoints(start, len) == Ok(()) && sample(1) != 0;
+
+                // Set a breakpoint at the entry of the function, as a debugger would do.
+                let page = start as usize & !4095;
+                let rwx = libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC;
+                if libc::mprotect(page as *mut _, start as usize - page + len, rwx) != 0 {
+                    return false;
+                }
+                let bp = breakpoint();
+                core::ptr::copy_nonoverlapping(bp.as_ptr(), start as *mut u8, bp.len());
+
+                clean && scan_breakpoints(start, len) == Err(DebugOffError::Breakpoint(0))
+            })
+        );
+    }
+}
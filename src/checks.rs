@@ -0,0 +1,59 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+#[cfg(feature = "std")]
+use crate::DebugOffError;
+
+/// Identifies one of the checks provided by the crate.
+///
+/// Used to select the checks executed by components that run them on behalf of the user, like
+/// the [watchdog](crate::watchdog).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// [`ptraceme_checked`](crate::ptraceme_checked).
+    Ptrace,
+    /// [`multi_ptraceme_checked`](crate::multi_ptraceme_checked).
+    MultiPtrace,
+    /// [`tracerpid_checked`](crate::tracerpid_checked).
+    TracerPid,
+    /// [`multi_ptraceme_checked`](crate::multi_ptraceme_checked) run in a
+    /// [timed region](crate::timing).
+    Timing,
+    /// [`integrity::verify_checked`](crate::integrity::verify_checked).
+    Integrity,
+    /// [`injection_checked`](crate::injection_checked).
+    Injection,
+    /// [`parent_checked`](crate::parent_checked).
+    Parent,
+    /// [`sigtrap_checked`](crate::sigtrap_checked).
+    SigTrap,
+    /// [`guardian::verify_checked`](crate::guardian::verify_checked).
+    Guardian,
+    /// [`dumpable_checked`](crate::dumpable_checked).
+    Dumpable,
+}
+
+#[cfg(feature = "std")]
+impl Check {
+    /// Run the check in the calling thread.
+    #[inline(always)]
+    pub fn run(self) -> Result<(), DebugOffError> {
+        match self {
+            Check::Ptrace => crate::ptraceme_checked(),
+            Check::MultiPtrace => crate::multi_ptraceme_checked(),
+            Check::TracerPid => crate::tracerpid_checked(),
+            Check::Timing => crate::timing::timed_checked(crate::multi_ptraceme_checked)?,
+            Check::Integrity => crate::integrity::verify_checked(),
+            Check::Injection => crate::injection_checked(),
+            Check::Parent => crate::parent_checked(),
+            Check::SigTrap => crate::sigtrap_checked(),
+            Check::Guardian => crate::guardian::verify_checked(),
+            Check::Dumpable => crate::dumpable_checked(),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use core::fmt;
+
+/// Error number returned by a failed system call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Errno(i32);
+
+impl Errno {
+    pub const EPERM: Errno = Errno(1);
+    pub const ENOENT: Errno = Errno(2);
+    pub const ESRCH: Errno = Errno(3);
+    pub const EINTR: Errno = Errno(4);
+    pub const EIO: Errno = Errno(5);
+    pub const EBADF: Errno = Errno(9);
+    pub const ECHILD: Errno = Errno(10);
+    pub const EAGAIN: Errno = Errno(11);
+    pub const ENOMEM: Errno = Errno(12);
+    pub const EACCES: Errno = Errno(13);
+    pub const EFAULT: Errno = Errno(14);
+    pub const EBUSY: Errno = Errno(16);
+    pub const EINVAL: Errno = Errno(22);
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+    pub const ENOSYS: Errno = Errno(38);
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub const ENOSYS: Errno = Errno(89);
+
+    /// Build an `Errno` from its raw (positive) value.
+    pub const fn new(raw: i32) -> Errno {
+        Errno(raw)
+    }
+
+    /// Return the raw (positive) value of the error number.
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    fn name(self) -> Option<&'static str> {
+        Some(match self {
+            Errno::EPERM => "EPERM",
+            Errno::ENOENT => "ENOENT",
+            Errno::ESRCH => "ESRCH",
+            Errno::EINTR => "EINTR",
+            Errno::EIO => "EIO",
+            Errno::EBADF => "EBADF",
+            Errno::ECHILD => "ECHILD",
+            Errno::EAGAIN => "EAGAIN",
+            Errno::ENOMEM => "ENOMEM",
+            Errno::EACCES => "EACCES",
+            Errno::EFAULT => "EFAULT",
+            Errno::EBUSY => "EBUSY",
+            Errno::EINVAL => "EINVAL",
+            Errno::ENOSYS => "ENOSYS",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", name, self.0),
+            None => write!(f, "errno {}", self.0),
+        }
+    }
+}
+
+/// Value returned by a raw system call: `Ok` with the returned value or `Err` with the error
+/// number.
+pub type SyscallResult = Result<usize, Errno>;
+
+/// Decode the value returned by a system call on architectures that report errors as values in
+/// the range `-4095..=-1`.
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+#[inline(always)]
+pub(crate) fn decode(ret: usize) -> SyscallResult {
+    if ret > -4096isize as usize {
+        Err(Errno((ret as isize).wrapping_neg() as i32))
+    } else {
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::Errno;
+
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+    #[test]
+    fn decode() {
+        assert_eq!(Ok(0), super::decode(0));
+        assert_eq!(Ok(usize::MAX - 4095), super::decode(usize::MAX - 4095));
+        assert_eq!(Err(Errno::EPERM), super::decode(-1isize as usize));
+        assert_eq!(Err(Errno::new(4095)), super::decode(-4095isize as usize));
+    }
+
+    #[test]
+    fn raw_syscall() {
+        let res =
+            unsafe { crate::arch::syscall4(crate::arch::SysNo::SYS_CLOSE, usize::MAX, 0, 0, 0) };
+        assert_eq!(Err(Errno::EBADF), res);
+    }
+}
@@ -0,0 +1,400 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Guardian process that keeps the process traced.
+//!
+//! `ptrace(PTRACE_TRACEME, ...)` only detects tracers attached before it is called. The guardian
+//! closes the gap the other way around: a small child process attaches to the process with
+//! `PTRACE_SEIZE` and keeps the tracer slot occupied, so that a debugger started later cannot
+//! attach. The guardian forwards every signal to the process (including `SIGTRAP`, so
+//! [`sigtrap_checked`](crate::sigtrap_checked) keeps working) and terminates when the process
+//! exits.
+//!
+//! Threads created after [`start`] are traced too. Threads created before are not: the guardian
+//! should be started early, before spawning threads. Since the slot is taken, the `ptrace` checks
+//! ([`ptraceme_checked`](crate::ptraceme_checked),
+//! [`multi_ptraceme_checked`](crate::multi_ptraceme_checked) and the timed variants) fail once
+//! the guardian is attached and must not be used together with it.
+//! [`tracerpid_checked`](crate::tracerpid_checked) accepts the guardian as the tracer.
+//!
+//! A debugger can kill the guardian to free the slot: [`verify_checked`] detects it and can be
+//! run periodically by the [watchdog](crate::watchdog) with [`Check::Guardian`](crate::Check).
+//!
+//! The guardian is a child of the process: it must not be reaped by the program (e.g., with
+//! `waitpid(-1, ...)`).
+//!
+//! ## Examples
+//!
+//! ```rust
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! debugoff::guardian::start_or_die();
+//!
+//! // ...
+//!
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! debugoff::guardian::verify_or_die();
+//! ```
+
+use crate::arch::{syscall1, syscall2, syscall3, syscall4, SigAction, SysNo, NSIG, SIGCHLD};
+use crate::errno::{Errno, SyscallResult};
+use crate::policy::the_end;
+use crate::tracerpid::status_field;
+use crate::DebugOffError;
+use core::mem;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+const PTRACE_CONT: usize = 7;
+const PTRACE_SEIZE: usize = 0x4206;
+const PTRACE_LISTEN: usize = 0x4208;
+const PTRACE_O_TRACECLONE: usize = 0x08;
+const PTRACE_O_TRACEEXEC: usize = 0x10;
+const PTRACE_EVENT_STOP: i32 = 128;
+
+const PR_SET_PTRACER: usize = 0x5961_6d61;
+const O_CLOEXEC: usize = 0o2000000;
+const WNOHANG: usize = 1;
+const WALL: usize = 0x4000_0000;
+
+// Serializes `start`.
+static LOCK: Mutex<()> = Mutex::new(());
+
+// PID of the guardian (0 if not started).
+static GUARDIAN: AtomicU32 = AtomicU32::new(0);
+
+/// Return `true` if `pid` is the guardian of the process.
+pub(crate) fn is_guardian(pid: u32) -> bool {
+    pid != 0 && GUARDIAN.load(Ordering::SeqCst) == pid
+}
+
+fn pipe() -> Result<[i32; 2], DebugOffError> {
+    let mut fds = [0i32; 2];
+    unsafe { syscall2(SysNo::SYS_PIPE2, fds.as_mut_ptr() as usize, O_CLOEXEC) }
+        .map_err(DebugOffError::Syscall)?;
+    Ok(fds)
+}
+
+fn close(fd: i32) {
+    let _res = unsafe { syscall1(SysNo::SYS_CLOSE, fd as usize) };
+}
+
+/// Run the system call `f`, retrying when interrupted.
+fn retry<F: Fn() -> SyscallResult>(f: F) -> SyscallResult {
+    loop {
+        match f() {
+            Err(Errno::EINTR) => continue,
+            res => return res,
+        }
+    }
+}
+
+fn read(fd: i32, buf: &mut [u8]) -> SyscallResult {
+    let (ptr, len) = (buf.as_mut_ptr() as usize, buf.len());
+    retry(|| unsafe { syscall3(SysNo::SYS_READ, fd as usize, ptr, len) })
+}
+
+fn write(fd: i32, buf: &[u8]) -> SyscallResult {
+    let (ptr, len) = (buf.as_ptr() as usize, buf.len());
+    retry(|| unsafe { syscall3(SysNo::SYS_WRITE, fd as usize, ptr, len) })
+}
+
+/// Create a child process with a raw `clone` system call (as `fork`, without running the `fork`
+/// handlers of the C library). The child must only use raw system calls.
+pub(crate) fn fork() -> SyscallResult {
+    unsafe { syscall4(SysNo::SYS_CLONE, SIGCHLD, 0, 0, 0) }
+}
+
+pub(crate) fn wait4(pid: i32, status: &mut i32, options: usize) -> SyscallResult {
+    unsafe {
+        syscall4(
+            SysNo::SYS_WAIT4,
+            pid as usize,
+            status as *mut i32 as usize,
+            options | WALL,
+            0,
+        )
+    }
+}
+
+fn ptrace(request: usize, pid: usize, data: usize) -> SyscallResult {
+    unsafe { syscall4(SysNo::SYS_PTRACE, request, pid, 0, data) }
+}
+
+pub(crate) fn exit_group(code: usize) -> ! {
+    loop {
+        let _res = unsafe { syscall1(SysNo::SYS_EXIT_GROUP, code) };
+    }
+}
+
+/// Body of the guardian process. Only raw system calls are used: the guardian is created with a
+/// raw `clone` and the state of the C library (and of any lock held by another thread at the
+/// time of the `clone`) cannot be trusted.
+fn guardian(pid: usize, go: i32, result: i32) -> ! {
+    unsafe {
+        // Leave the session of the process (e.g., `Ctrl-C` must reach only the process, which
+        // terminates and then makes the guardian terminate) and drop the signal handlers
+        // inherited from it.
+        let _res = syscall1(SysNo::SYS_SETSID, 0);
+        let dfl = SigAction::empty();
+        for sig in 1..=NSIG {
+            let _res = syscall4(
+                SysNo::SYS_RT_SIGACTION,
+                sig,
+                &dfl as *const SigAction as usize,
+                0,
+                mem::size_of_val(&dfl.mask),
+            );
+        }
+    }
+
+    // Wait until the process allowed the guardian to trace it.
+    let mut b = [0u8; 1];
+    if read(go, &mut b) != Ok(1) {
+        exit_group(1);
+    }
+    let res = ptrace(PTRACE_SEIZE, pid, PTRACE_O_TRACECLONE | PTRACE_O_TRACEEXEC);
+    let errno = match res {
+        Ok(_) => 0i32,
+        Err(e) => e.raw(),
+    }
+    .to_ne_bytes();
+    let _res = write(result, &errno);
+    if res.is_err() {
+        exit_group(1);
+    }
+
+    // Do not keep the files of the process open (e.g., sockets closed by the process).
+    if unsafe { syscall3(SysNo::SYS_CLOSE_RANGE, 0, u32::MAX as usize, 0) }.is_err() {
+        for fd in 0..4096 {
+            close(fd);
+        }
+    }
+
+    loop {
+        let mut status = 0i32;
+        let tid = match wait4(-1, &mut status, 0) {
+            Ok(tid) => tid,
+            Err(Errno::EINTR) => continue,
+            // No tracee left.
+            Err(_) => exit_group(0),
+        };
+
+        if status & 0xFF != 0x7F {
+            // A thread exited: the process has exited when its main thread is reported.
+            if tid == pid {
+                exit_group(0);
+            }
+            continue;
+        }
+
+        let sig = ((status >> 8) & 0xFF) as usize;
+        let event = status >> 16;
+        let (request, sig) = match event {
+            // Signal delivery stop: forward the signal.
+            0 => (PTRACE_CONT, sig),
+            // Group stop (stopped by `SIGSTOP`, `SIGTSTP`, ...): stay stopped until `SIGCONT`.
+            PTRACE_EVENT_STOP if sig != crate::arch::SIGTRAP => (PTRACE_LISTEN, 0),
+            // First stop of a new thread, `clone` and `execve` events.
+            _ => (PTRACE_CONT, 0),
+        };
+        let _res = ptrace(request, tid, sig);
+    }
+}
+
+/// Start the guardian process and return its PID.
+///
+/// A child process is created with a raw `clone` system call. The process allows it to trace
+/// itself with `prctl(PR_SET_PTRACER, ...)` (required when the Yama LSM restricts `ptrace`) and
+/// the guardian attaches with `PTRACE_SEIZE`. The function returns once the guardian is attached.
+/// If the guardian is already running, its PID is returned.
+///
+/// If the process is already traced (e.g., by a debugger or by the `ptrace` checks), the guardian
+/// cannot attach and the function returns [`DebugOffError::Syscall`](crate::DebugOffError) with
+/// the error of `PTRACE_SEIZE` (usually `EPERM`).
+pub fn start() -> Result<u32, DebugOffError> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let running = GUARDIAN.load(Ordering::SeqCst);
+    if running != 0 && verify_checked().is_ok() {
+        return Ok(running);
+    }
+
+    let go = pipe()?;
+    let result = match pipe() {
+        Ok(fds) => fds,
+        Err(e) => {
+            go.iter().for_each(|fd| close(*fd));
+            return Err(e);
+        }
+    };
+
+    let pid = crate::aa::getpid() as usize;
+    let res = fork();
+    if res == Ok(0) {
+        guardian(pid, go[0], result[1]);
+    }
+    close(go[0]);
+    close(result[1]);
+
+    let mut errno = [0u8; 4];
+    let res = res.map_err(DebugOffError::Syscall).map(|child| {
+        // Fails with `EINVAL` when Yama is not enabled.
+        let _res = unsafe { syscall2(SysNo::SYS_PRCTL, PR_SET_PTRACER, child) };
+        let _res = write(go[1], &[1]);
+        (child, read(result[0], &mut errno))
+    });
+    close(go[1]);
+    close(result[0]);
+    let (child, n) = res?;
+
+    match (n, i32::from_ne_bytes(errno)) {
+        (Ok(4), 0) => {
+            GUARDIAN.store(child as u32, Ordering::SeqCst);
+            Ok(child as u32)
+        }
+        (n, errno) => {
+            let mut status = 0i32;
+            let _res = wait4(child as i32, &mut status, 0);
+            Err(match n {
+                Ok(4) => DebugOffError::Syscall(Errno::new(errno)),
+                _ => DebugOffError::GuardianLost(child as u32),
+            })
+        }
+    }
+}
+
+/// Start the guardian process.
+///
+/// This is a wrapper around [`start`]: if the guardian cannot be started, the function reacts
+/// according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+/// `exit_group(0)`).
+pub fn start_or_die() {
+    if start().is_err() && crate::probe::is_supported(crate::Check::Guardian) {
+        the_end();
+    }
+}
+
+/// Verify that the guardian is running and tracing the process.
+///
+/// Returns [`DebugOffError::GuardianLost`](crate::DebugOffError::GuardianLost) if the guardian
+/// has not been started, if it terminated or if it is not the tracer of the process anymore, and
+/// [`DebugOffError::TracerPid`](crate::DebugOffError::TracerPid) if the guardian itself is traced.
+pub fn verify_checked() -> Result<(), DebugOffError> {
+    let pid = GUARDIAN.load(Ordering::SeqCst);
+    if pid == 0 {
+        return Err(DebugOffError::GuardianLost(0));
+    }
+
+    let mut status = 0i32;
+    if wait4(pid as i32, &mut status, WNOHANG) != Ok(0) {
+        return Err(DebugOffError::GuardianLost(pid));
+    }
+    if status_field(b"/proc/self/status\0", b"TracerPid")? != pid {
+        return Err(DebugOffError::GuardianLost(pid));
+    }
+
+    let mut path = crate::procfs::ProcPath::new();
+    path.push(b"/proc/").push_u32(pid).push(b"/status");
+    match status_field(path.as_bytes(), b"TracerPid")? {
+        0 => Ok(()),
+        tracer => Err(DebugOffError::TracerPid(tracer)),
+    }
+}
+
+/// Verify that the guardian is running and tracing the process.
+///
+/// This is a wrapper around [`verify_checked`]: if the verification fails, the function reacts
+/// according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+/// `exit_group(0)`).
+pub fn verify_or_die() {
+    if verify_checked().is_err() && crate::probe::is_supported(crate::Check::Guardian) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{start, verify_checked};
+    use crate::aa::test::run_in_child;
+    use crate::DebugOffError;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn guardian() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let pid = match start() {
+                    Ok(pid) => pid,
+                    Err(_) => return false,
+                };
+                let traced = thread::spawn(|| crate::tracerpid_checked().is_ok())
+                    .join()
+                    .unwrap();
+
+                start() == Ok(pid)
+                    && verify_checked().is_ok()
+                    && traced
+                    && crate::tracerpid_checked().is_ok()
+                    && crate::sigtrap_checked().is_ok()
+                    && crate::ptraceme_checked().is_err()
+            })
+        );
+    }
+
+    #[test]
+    fn lost() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                let pid = start().unwrap();
+                libc::kill(pid as i32, libc::SIGKILL);
+                thread::sleep(Duration::from_millis(50));
+                verify_checked() == Err(DebugOffError::GuardianLost(pid))
+                    && crate::tracerpid_checked().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn shutdown() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                // Become the parent of the guardian once the process that started it exits.
+                libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1);
+                let mut fds = [0; 2];
+                libc::pipe(fds.as_mut_ptr());
+                if libc::fork() == 0 {
+                    let pid = start().unwrap_or(0);
+                    libc::write(fds[1], &pid as *const u32 as *const _, 4);
+                    libc::_exit(0);
+                }
+
+                let mut pid = 0u32;
+                libc::read(fds[0], &mut pid as *mut u32 as *mut _, 4);
+                if pid == 0 {
+                    return false;
+                }
+
+                let deadline = Instant::now() + Duration::from_secs(5);
+                let mut status = 0;
+                while Instant::now() < deadline {
+                    if libc::waitpid(pid as i32, &mut status, libc::WNOHANG) == pid as i32 {
+                        return libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+                false
+            })
+        );
+    }
+}
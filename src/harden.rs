@@ -0,0 +1,206 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::arch::{syscall1, syscall2, SysNo};
+use crate::errno::Errno;
+use crate::policy::the_end;
+use crate::DebugOffError;
+
+const PR_GET_DUMPABLE: usize = 3;
+const PR_SET_DUMPABLE: usize = 4;
+const RLIMIT_CORE: usize = 4;
+const MCL_CURRENT: usize = 1;
+const MCL_FUTURE: usize = 2;
+
+/// Steps performed by [`harden_process`].
+#[derive(Debug, Clone, Default)]
+pub struct HardenConfig {
+    lock_memory: bool,
+}
+
+impl HardenConfig {
+    /// Default configuration: the process is made non-dumpable and core dumps are disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock the current and future memory of the process in RAM, so that it is never written to
+    /// the swap. Requires `CAP_IPC_LOCK` or a large enough `RLIMIT_MEMLOCK`.
+    pub fn lock_memory(mut self, lock_memory: bool) -> Self {
+        self.lock_memory = lock_memory;
+        self
+    }
+}
+
+/// Outcome of each step of [`harden_process`] (`Err` with the error of the system call).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HardenReport {
+    /// `prctl(PR_SET_DUMPABLE, 0)`.
+    pub non_dumpable: Result<(), Errno>,
+    /// `setrlimit(RLIMIT_CORE, 0)`.
+    pub core_dumps_disabled: Result<(), Errno>,
+    /// `mlockall(MCL_CURRENT | MCL_FUTURE)` (`None` if not requested).
+    pub memory_locked: Option<Result<(), Errno>>,
+}
+
+impl HardenReport {
+    /// Return `true` if every requested step succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.non_dumpable.is_ok()
+            && self.core_dumps_disabled.is_ok()
+            && self.memory_locked.unwrap_or(Ok(())).is_ok()
+    }
+}
+
+/// Protect the memory of the process from being read through other channels than a debugger.
+///
+/// The process is made non-dumpable with `prctl(PR_SET_DUMPABLE, 0)`: the kernel then denies
+/// `/proc/<pid>/mem`, `process_vm_readv` and `ptrace` to processes of the same user without
+/// `CAP_SYS_PTRACE`, and never writes a core dump. Core dumps are also disabled through
+/// `setrlimit(RLIMIT_CORE, 0)`, which is inherited by the child processes. Optionally, the memory
+/// is locked with `mlockall` (see [`HardenConfig::lock_memory`]). All the steps are performed with
+/// raw system calls, and each of them is attempted even if the previous ones failed.
+///
+/// The files under `/proc/self` become owned by root: files readable only by their owner (e.g.,
+/// `/proc/self/environ`, used by [`injection_checked`](crate::injection_checked)) cannot be read
+/// anymore by an unprivileged process. Likewise, the [guardian](crate::guardian) cannot attach
+/// anymore: it must be started before.
+///
+/// The kernel makes the process dumpable again on some credential changes (e.g., after
+/// `execve`-ing a set-user-ID program or changing the effective user): [`dumpable_checked`]
+/// detects it.
+///
+/// ## Examples
+///
+/// ```rust
+/// use debugoff::HardenConfig;
+///
+/// let report = debugoff::harden_process(HardenConfig::new().lock_memory(true));
+/// if !report.is_complete() {
+///     eprintln!("Hardening incomplete: {:?}", report);
+/// }
+/// ```
+pub fn harden_process(config: HardenConfig) -> HardenReport {
+    let non_dumpable = unsafe { syscall2(SysNo::SYS_PRCTL, PR_SET_DUMPABLE, 0) }.map(|_| ());
+
+    let limit = [0usize; 2];
+    let core_dumps_disabled =
+        unsafe { syscall2(SysNo::SYS_SETRLIMIT, RLIMIT_CORE, limit.as_ptr() as usize) }.map(|_| ());
+
+    let memory_locked = config
+        .lock_memory
+        .then(|| unsafe { syscall1(SysNo::SYS_MLOCKALL, MCL_CURRENT | MCL_FUTURE) }.map(|_| ()));
+
+    HardenReport {
+        non_dumpable,
+        core_dumps_disabled,
+        memory_locked,
+    }
+}
+
+/// Check whether the process is non-dumpable (see [`harden_process`]).
+///
+/// Returns [`DebugOffError::Dumpable`](crate::DebugOffError::Dumpable) if the process is
+/// dumpable, e.g., because it has never been hardened or because the setting has been switched
+/// back on.
+///
+/// ## Examples
+///
+/// ```rust
+/// debugoff::harden_process(debugoff::HardenConfig::new());
+///
+/// // ...
+///
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::dumpable_checked() {
+///     eprintln!("Hardening reverted: {}", e);
+/// }
+/// ```
+#[inline(always)]
+pub fn dumpable_checked() -> Result<(), DebugOffError> {
+    match unsafe { syscall1(SysNo::SYS_PRCTL, PR_GET_DUMPABLE) } {
+        Ok(0) => Ok(()),
+        Ok(_) => Err(DebugOffError::Dumpable),
+        Err(e) => Err(DebugOffError::Syscall(e)),
+    }
+}
+
+/// Check whether the process is non-dumpable (see [`harden_process`]).
+///
+/// This is a wrapper around [`dumpable_checked`]: if the process is dumpable (or the check cannot
+/// be performed), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::dumpable_or_die();
+/// ```
+#[inline(always)]
+pub fn dumpable_or_die() {
+    if dumpable_checked().is_err() && crate::probe::is_supported(crate::Check::Dumpable) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{dumpable_checked, harden_process, HardenConfig};
+    use crate::aa::test::run_in_child;
+    use crate::DebugOffError;
+
+    #[test]
+    fn harden() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                let before = dumpable_checked();
+                let report = harden_process(HardenConfig::new());
+
+                let mut limit: libc::rlimit = std::mem::zeroed();
+                libc::getrlimit(libc::RLIMIT_CORE, &mut limit);
+                before == Err(DebugOffError::Dumpable)
+                    && report.is_complete()
+                    && report.memory_locked.is_none()
+                    && dumpable_checked().is_ok()
+                    && limit.rlim_cur == 0
+                    && limit.rlim_max == 0
+                    && libc::prctl(libc::PR_GET_DUMPABLE) == 0
+            })
+        );
+    }
+
+    #[test]
+    fn lock_memory() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                // `mlockall` may fail with a small `RLIMIT_MEMLOCK`: only the report is checked.
+                let report = harden_process(HardenConfig::new().lock_memory(true));
+                report.non_dumpable.is_ok() && report.memory_locked.is_some()
+            })
+        );
+    }
+
+    #[test]
+    fn reverted() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                harden_process(HardenConfig::new());
+                let hardened = dumpable_checked().is_ok();
+                libc::prctl(libc::PR_SET_DUMPABLE, 1);
+                hardened && dumpable_checked() == Err(DebugOffError::Dumpable)
+            })
+        );
+    }
+}
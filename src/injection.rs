@@ -0,0 +1,302 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::policy::the_end;
+use crate::procfs::{self, Mapping};
+use crate::DebugOffError;
+use std::sync::Mutex;
+
+// File name prefixes of the libraries loaded by programs linked with the Rust standard library
+// (including the ones loaded by the C library itself, like the NSS modules). They are allowed only
+// in the system library directories (see `SYSTEM_DIRS`).
+const DEFAULT_ALLOWED: &[&[u8]] = &[
+    b"ld-",
+    b"ld.so",
+    b"libc.so",
+    b"libc-",
+    b"libm.so",
+    b"libm-",
+    b"libgcc_s",
+    b"libpthread",
+    b"libdl",
+    b"librt",
+    b"libutil",
+    b"libresolv",
+    b"libnss_",
+];
+
+// System library directories. The libraries can also be in a multiarch subdirectory (e.g.,
+// `/usr/lib/x86_64-linux-gnu/`).
+const SYSTEM_DIRS: &[&[u8]] = &[
+    b"/lib/",
+    b"/lib32/",
+    b"/lib64/",
+    b"/usr/lib/",
+    b"/usr/lib32/",
+    b"/usr/lib64/",
+];
+
+// Variables making the dynamic loader load additional libraries.
+const LOADER_VARIABLES: &[&[u8]] = &[b"LD_PRELOAD=", b"LD_AUDIT="];
+
+// Maximum length of the path of the executable.
+const PATH_SIZE: usize = 512;
+
+static ALLOWED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Register a shared object which is legitimately loaded by the process.
+///
+/// If `name` starts with `/`, it is matched against the beginning of the path of the mapped file
+/// (e.g., `/opt/app/lib/` allows every library in that directory). Otherwise, it is matched
+/// against the beginning of the file name (e.g., `libssl.so` allows `libssl.so.3`).
+///
+/// The C library, the dynamic loader and the libraries used by the Rust standard library are
+/// always allowed when they are loaded from the system library directories (`/lib`, `/lib64`,
+/// `/usr/lib`, `/usr/lib64`, ... and their multiarch subdirectories, e.g.
+/// `/usr/lib/x86_64-linux-gnu`).
+///
+/// ## Examples
+///
+/// ```rust
+/// debugoff::allow_library("libssl.so");
+/// debugoff::allow_library("/opt/app/lib/");
+/// ```
+pub fn allow_library(name: &'static str) {
+    ALLOWED.lock().unwrap_or_else(|e| e.into_inner()).push(name);
+}
+
+/// Return `true` if `path` is a file of a system library directory (or of one of its multiarch
+/// subdirectories).
+fn in_system_dir(path: &[u8]) -> bool {
+    SYSTEM_DIRS
+        .iter()
+        .filter_map(|d| path.strip_prefix(*d))
+        .any(|rest| {
+            let mut parts = rest.rsplit(|c| *c == b'/');
+            let _file = parts.next();
+            match (parts.next(), parts.next()) {
+                (None, _) => true,
+                (Some(arch), None) => arch.windows(7).any(|w| w == b"-linux-"),
+                _ => false,
+            }
+        })
+}
+
+fn is_allowed(path: &[u8], allowed: &[&str]) -> bool {
+    let file = path.rsplit(|c| *c == b'/').next().unwrap_or(path);
+    (in_system_dir(path) && DEFAULT_ALLOWED.iter().any(|p| file.starts_with(p)))
+        || allowed.iter().map(|a| a.as_bytes()).any(|a| {
+            if a.starts_with(b"/") {
+                path.starts_with(a)
+            } else {
+                file.starts_with(a)
+            }
+        })
+}
+
+/// Check an entry (`NAME=value`) of the initial environment.
+fn check_variable(entry: &[u8]) -> Result<(), DebugOffError> {
+    match LOADER_VARIABLES.iter().find_map(|v| entry.strip_prefix(*v)) {
+        Some(value) if !value.is_empty() => Err(DebugOffError::PreloadVariable),
+        _ => Ok(()),
+    }
+}
+
+/// Check an executable mapping, given the path of the executable and the allowed libraries.
+fn check_mapping(m: &Mapping, exe: &[u8], allowed: &[&str]) -> Result<(), DebugOffError> {
+    if !m.is_exec() {
+        return Ok(());
+    }
+    match m.path {
+        b"[vdso]" | b"[vsyscall]" => Ok(()),
+        // Anonymous memory or other pseudo-paths (e.g., `[uprobes]`).
+        p if p.is_empty() || p.starts_with(b"[") => {
+            Err(DebugOffError::AnonymousExecutable(m.start))
+        }
+        p if p == exe || is_allowed(p, allowed) => Ok(()),
+        _ => Err(DebugOffError::UnexpectedLibrary(m.start)),
+    }
+}
+
+/// Look for code injected in the process.
+///
+/// The initial environment (`/proc/self/environ`) is checked for the `LD_PRELOAD` and `LD_AUDIT`
+/// variables and `/proc/self/maps` is checked for executable mappings of shared objects that have
+/// not been [allowed](allow_library) and for anonymous executable mappings. Files are opened and
+/// read with raw system calls.
+///
+/// Returns [`DebugOffError::PreloadVariable`](crate::DebugOffError::PreloadVariable),
+/// [`DebugOffError::UnexpectedLibrary`](crate::DebugOffError::UnexpectedLibrary) or
+/// [`DebugOffError::AnonymousExecutable`](crate::DebugOffError::AnonymousExecutable) (with the
+/// address of the mapping). Programs which generate code at runtime (e.g., with a JIT compiler)
+/// have anonymous executable mappings and should not use this check.
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::injection_checked() {
+///     eprintln!("Injection detected: {}", e);
+/// }
+/// ```
+#[inline(always)]
+pub fn injection_checked() -> Result<(), DebugOffError> {
+    procfs::for_each_record(b"/proc/self/environ\0", 0, check_variable)?;
+
+    let mut exe = [0u8; PATH_SIZE];
+    let len = procfs::read_link(b"/proc/self/exe\0", &mut exe)?;
+    let allowed = ALLOWED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+
+    procfs::for_each_line(b"/proc/self/maps\0", |line| {
+        let m = procfs::parse_mapping(line).ok_or(DebugOffError::ProcFormat)?;
+        check_mapping(&m, &exe[..len], &allowed)
+    })
+}
+
+/// Look for code injected in the process.
+///
+/// This is a wrapper around [`injection_checked`]: if an injection is detected (or the check
+/// cannot be performed), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::injection_or_die();
+/// ```
+#[inline(always)]
+pub fn injection_or_die() {
+    if injection_checked().is_err() && crate::probe::is_supported(crate::Check::Injection) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{check_mapping, check_variable};
+    use crate::aa::test::run_in_child;
+    use crate::procfs::parse_mapping;
+    use crate::DebugOffError;
+
+    const EXE: &[u8] = b"/usr/bin/app";
+
+    fn check(line: &[u8], allowed: &[&str]) -> Result<(), DebugOffError> {
+        check_mapping(&parse_mapping(line).unwrap(), EXE, allowed)
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(Ok(()), check_variable(b"PATH=/usr/bin"));
+        assert_eq!(Ok(()), check_variable(b"LD_PRELOAD="));
+        assert_eq!(Ok(()), check_variable(b"MY_LD_PRELOAD=/tmp/x.so"));
+        assert_eq!(
+            Err(DebugOffError::PreloadVariable),
+            check_variable(b"LD_PRELOAD=/tmp/hook.so")
+        );
+        assert_eq!(
+            Err(DebugOffError::PreloadVariable),
+            check_variable(b"LD_AUDIT=/tmp/audit.so")
+        );
+    }
+
+    #[test]
+    fn mappings() {
+        let ok: &[&[u8]] = &[
+            b"55d0c000-55d0d000 r-xp 00001000 08:01 1234    /usr/bin/app",
+            b"7f00c000-7f00d000 r-xp 00028000 08:01 42      /usr/lib/x86_64-linux-gnu/libc.so.6",
+            b"7f00c000-7f00d000 r-xp 00001000 08:01 43      /lib/ld-linux-aarch64.so.1",
+            b"7f00e000-7f00f000 r--p 00000000 08:01 44      /tmp/hook.so",
+            b"7f010000-7f011000 rw-p 00000000 00:00 0 ",
+            b"7ffd1000-7ffd2000 r-xp 00000000 00:00 0       [vdso]",
+        ];
+        for line in ok {
+            assert_eq!(
+                Ok(()),
+                check(line, &[]),
+                "{}",
+                String::from_utf8_lossy(line)
+            );
+        }
+
+        let lib = b"7f00e000-7f00f000 r-xp 00001000 08:01 44      /tmp/hook.so";
+        assert_eq!(
+            Err(DebugOffError::UnexpectedLibrary(0x7f00e000)),
+            check(lib, &[])
+        );
+        assert_eq!(Ok(()), check(lib, &["hook.so"]));
+        assert_eq!(Ok(()), check(lib, &["/tmp/"]));
+        assert!(check(lib, &["/opt/"]).is_err());
+
+        let memfd = b"7f00e000-7f00f000 r-xp 00000000 00:01 45      /memfd:x (deleted)";
+        assert!(check(memfd, &[]).is_err());
+
+        let anon = b"7f010000-7f011000 rwxp 00000000 00:00 0 ";
+        assert_eq!(
+            Err(DebugOffError::AnonymousExecutable(0x7f010000)),
+            check(anon, &[])
+        );
+        let uprobes = b"7f010000-7f011000 r-xp 00000000 00:00 0       [uprobes]";
+        assert!(check(uprobes, &[]).is_err());
+    }
+
+    #[test]
+    fn system_directories() {
+        let line = |path: &str| format!("7f00c000-7f00d000 r-xp 00028000 08:01 42      {}", path);
+        for path in [
+            "/usr/lib/x86_64-linux-gnu/libc.so.6",
+            "/lib64/ld-linux-x86-64.so.2",
+            "/usr/lib/libnss_files.so.2",
+            "/lib/aarch64-linux-gnu/libgcc_s.so.1",
+        ] {
+            assert_eq!(Ok(()), check(line(path).as_bytes(), &[]), "{}", path);
+        }
+        for path in [
+            "/tmp/libc.so.6",
+            "/tmp/libc.so.evil",
+            "/dev/shm/ld-x.so",
+            "/home/user/libnss_foo.so",
+            "/usr/lib/x86_64-linux-gnu/../../../tmp/libc.so.6",
+            "/usr/lib/plugins/libc.so.6",
+            "/usr/local/lib/libc.so.6",
+        ] {
+            assert!(check(line(path).as_bytes(), &[]).is_err(), "{}", path);
+        }
+        // Explicitly allowed.
+        assert_eq!(Ok(()), check(line("/tmp/libc.so.6").as_bytes(), &["/tmp/"]));
+    }
+
+    #[test]
+    fn anonymous_executable() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                // Only anonymous mappings are tested here.
+                super::allow_library("/");
+                if super::injection_checked().is_err() {
+                    return false;
+                }
+
+                let page = libc::mmap(
+                    std::ptr::null_mut(),
+                    4096,
+                    libc::PROT_READ | libc::PROT_EXEC,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                );
+                page != libc::MAP_FAILED
+                    && super::injection_checked()
+                        == Err(DebugOffError::AnonymousExecutable(page as usize))
+            })
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Code integrity checks.
+//!
+//! Patching a single comparison or call in the shipped binary (e.g., the call to the response
+//! policy) is enough to defeat every other check. The integrity check computes a checksum of
+//! regions of the executable mapped in memory (e.g., the `.text` section) and compares it with
+//! the value computed after linking.
+//!
+//! The expected values are stored in a reserved section of the binary ([`SEAL_SECTION`]), which
+//! is created by the library with an empty placeholder (the seal) and filled after linking by the
+//! `debugoff-seal` tool of the workspace. Until the binary is sealed, the check fails with
+//! [`DebugOffError::NotSealed`](crate::DebugOffError::NotSealed).
+//!
+//! ```text
+//! cargo build --release
+//! cargo run -p debugoff-seal -- target/release/app
+//! ```
+//!
+//! By default, `.text` and the sections of the [`critical`](crate::scan_breakpoints) functions are
+//! sealed; other sections, functions (when the symbol table is available) or address ranges can
+//! be selected (see `debugoff-seal --help`).
+//!
+//! The seal is made of native endian 64-bit words:
+//!
+//! | Offset | Content                                                          |
+//! |--------|------------------------------------------------------------------|
+//! | 0      | [`SEAL_MAGIC`] (16 bytes)                                        |
+//! | 16     | Link-time virtual address of the seal (0 until sealed)           |
+//! | 24     | Number of regions (at most [`MAX_REGIONS`])                      |
+//! | 32     | [`MAX_REGIONS`] × (virtual address, length, [`checksum`])        |
+//!
+//! The virtual addresses are the link-time ones: at runtime, they are relocated by the distance
+//! between the runtime and the link-time address of the seal, so position independent
+//! executables are supported.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! debugoff::integrity::verify_or_die();
+//! ```
+
+use crate::policy::the_end;
+use crate::DebugOffError;
+use core::ptr;
+
+/// Name of the section holding the seal.
+pub const SEAL_SECTION: &str = ".debugoff_seal";
+
+/// Magic value at the start of the seal.
+pub const SEAL_MAGIC: [u8; 16] = *b"DEBUGOFF-SEAL-01";
+
+/// Maximum number of regions checked.
+pub const MAX_REGIONS: usize = 16;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Region {
+    vaddr: u64,
+    len: u64,
+    checksum: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Seal {
+    magic: [u8; 16],
+    vaddr: u64,
+    count: u64,
+    regions: [Region; MAX_REGIONS],
+}
+
+#[link_section = ".debugoff_seal"]
+static SEAL: Seal = Seal {
+    magic: SEAL_MAGIC,
+    vaddr: 0,
+    count: 0,
+    regions: [Region {
+        vaddr: 0,
+        len: 0,
+        checksum: 0,
+    }; MAX_REGIONS],
+};
+
+/// Checksum (64-bit FNV-1a) of `data`, as stored in the seal.
+pub fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Check the regions listed in `seal`, given the runtime address of the seal.
+fn verify(seal: &Seal, addr: usize) -> Result<(), DebugOffError> {
+    if seal.magic != SEAL_MAGIC || seal.vaddr == 0 || seal.count == 0 {
+        return Err(DebugOffError::NotSealed);
+    }
+    let count = seal.count.min(MAX_REGIONS as u64) as usize;
+    let base = addr.wrapping_sub(seal.vaddr as usize);
+
+    for (i, region) in seal.regions[..count].iter().enumerate() {
+        let start = base.wrapping_add(region.vaddr as usize);
+        // The regions are part of the loaded segments of the binary (checked by the tool which
+        // writes the seal).
+        let data = unsafe { core::slice::from_raw_parts(start as *const u8, region.len as usize) };
+        if checksum(data) != region.checksum {
+            return Err(DebugOffError::Integrity(i));
+        }
+    }
+    Ok(())
+}
+
+/// Verify the checksums of the regions listed in the seal.
+///
+/// Returns [`DebugOffError::Integrity`](crate::DebugOffError::Integrity) with the index of the
+/// first modified region, or [`DebugOffError::NotSealed`](crate::DebugOffError::NotSealed) if the
+/// seal has not been written after linking.
+#[inline(never)]
+pub fn verify_checked() -> Result<(), DebugOffError> {
+    // The seal is modified after compilation: its content must not be known to the optimizer.
+    let seal = unsafe { ptr::read_volatile(ptr::addr_of!(SEAL)) };
+    verify(&seal, ptr::addr_of!(SEAL) as usize)
+}
+
+/// Verify the checksums of the regions listed in the seal.
+///
+/// This is a wrapper around [`verify_checked`]: if a region has been modified (or the binary has
+/// not been sealed), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+#[inline(always)]
+pub fn verify_or_die() {
+    if verify_checked().is_err() && crate::probe::is_supported(crate::Check::Integrity) {
+        the_end();
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::{checksum, verify, Region, Seal, MAX_REGIONS, SEAL_MAGIC};
+    use crate::DebugOffError;
+
+    #[test]
+    fn fnv1a() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, checksum(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, checksum(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, checksum(b"foobar"));
+    }
+
+    #[test]
+    fn unsealed() {
+        assert_eq!(Err(DebugOffError::NotSealed), super::verify_checked());
+    }
+
+    #[test]
+    fn regions() {
+        let code = [0x90u8; 256];
+        let mut seal = Seal {
+            magic: SEAL_MAGIC,
+            // Link-time addresses relative to a seal at 0x1000.
+            vaddr: 0x1000,
+            count: 2,
+            regions: [Region {
+                vaddr: 0,
+                len: 0,
+                checksum: 0,
+            }; MAX_REGIONS],
+        };
+        seal.regions[0] = Region {
+            vaddr: 0x2000,
+            len: 128,
+            checksum: checksum(&code[..128]),
+        };
+        seal.regions[1] = Region {
+            vaddr: 0x2080,
+            len: 128,
+            checksum: checksum(&code[128..]),
+        };
+        let addr = code.as_ptr() as usize - 0x1000;
+
+        assert_eq!(Ok(()), verify(&seal, addr));
+        let patched = [&code[..200], &[0xCC], &code[201..]].concat();
+        let addr = patched.as_ptr() as usize - 0x1000;
+        assert_eq!(Err(DebugOffError::Integrity(1)), verify(&seal, addr));
+
+        seal.vaddr = 0;
+        assert_eq!(Err(DebugOffError::NotSealed), verify(&seal, addr));
+    }
+}
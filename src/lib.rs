@@ -15,20 +15,52 @@
 //! It is currently based on `ptrace` anti-analysis trick and provides the following main features:
 //!
 //! * Direct syscall invocation without relying on libc (this makes LD_PRELOAD bypass mechanism
-//! ineffective);
+//!   ineffective);
 //!
 //! * Multiple `ptrace` syscall invocations. Each call to `ptrace` must return the expected value
-//! (i.e., 0 at the first invocation and -1 thereafter) and contributes to the computation of an
-//! "`offset`" value that, at the end of the `ptrace` call chain, must match an expected value (see
-//! [here](https://seblau.github.io/posts/linux-anti-debugging)). If ptrace returns an unexpcted
-//! value or the "`offset`" value does not match, the process is terminated;
+//!   (i.e., 0 at the first invocation and -1 thereafter) and contributes to the computation of an
+//!   "`offset`" value that, at the end of the `ptrace` call chain, must match an expected value (see
+//!   [here](https://seblau.github.io/posts/linux-anti-debugging)). If ptrace returns an unexpcted
+//!   value or the "`offset`" value does not match, the process is terminated;
 //!
 //! * 'ptrace' is called in nested loops. The loops are unrolled and the number of iterations is
-//! randomized at each compilation. Moreover, also the "`offset`" value is radomized at each
-//! iteration;
+//!   randomized at each compilation (the ranges can be configured with
+//!   [`multi_ptraceme_or_die!`]). Moreover, also the "`offset`" value is radomized at each
+//!   iteration (the runtime random values are drawn from a generator seeded by the kernel through
+//!   `getrandom` or `AT_RANDOM`);
+//!
+//! * `TracerPid` inspection of `/proc/self/status` and of the status file of every thread (see
+//!   [`tracerpid_or_die`]). `/proc` is read with raw system calls too;
+//!
+//! * Detection of libraries injected with `LD_PRELOAD`/`LD_AUDIT`, of unexpected shared objects
+//!   and of anonymous executable memory (see [`injection_checked`] and [`allow_library`]);
+//!
+//! * Inspection of the parent process, which is compared with a list of debuggers and analysis
+//!   tools stored obfuscated in the binary (see [`parent_checked`] and [`deny_parent!`]);
+//!
+//! * `SIGTRAP` self-test which detects debuggers intercepting the breakpoint instructions executed
+//!   by the process (see [`sigtrap_checked`]);
+//!
+//! * Guardian process which attaches to the process with `ptrace` and keeps the tracer slot
+//!   occupied (see [`guardian`]);
+//!
+//! * Process hardening against memory extraction through `/proc/<pid>/mem`, `process_vm_readv`
+//!   and core dumps (see [`harden_process`] and [`dumpable_checked`]);
+//!
+//! * Timing checks which detect single-stepping and breakpoints in protected regions (see
+//!   [`timing`]);
+//!
+//! * Software breakpoint scanner which looks for the breakpoint instructions of the target
+//!   architecture in a region of code (see [`scan_breakpoints`]);
+//!
+//! * Integrity check of the code mapped in memory against the checksums written in the binary
+//!   after linking (see [`integrity`]);
+//!
+//! * Host probe which reports the checks that can run reliably in the current environment (e.g.,
+//!   in containers forbidding `ptrace`) and makes the other ones non-fatal (see [`probe`]);
 //!
 //! * The generated code can be obfuscated even more by enabling the `obfuscate` feature which
-//! relies on [goldberg crate](https://crates.io/crates/goldberg);
+//!   relies on [goldberg crate](https://crates.io/crates/goldberg);
 //!
 //!
 //! To use the crate, add it to your dependencies:
@@ -54,6 +86,26 @@
 //! panic = "abort"
 //! ```
 //!
+//! ## `no_std`
+//!
+//! The `std` feature is enabled by default. Without it, the crate builds under `#![no_std]` and
+//! provides the raw system call layer, the `ptrace` checks ([`ptraceme_checked`],
+//! [`multi_ptraceme_checked`] and their `*_or_die` variants) and the [`ResponsePolicy`], which
+//! is enough for small static loaders and `#![no_main]` binaries:
+//!
+//! ```text
+//! [dependencies]
+//! debugoff = { version = "0.2.2", default-features = false }
+//! ```
+//!
+//! Without the standard library there is no thread-local storage: the state of the `ptrace`
+//! checks is shared by all the threads, which are told apart by their TID (as when
+//! [`enable_global_state`] is called). The exit of a thread cannot be observed, so at most 64
+//! threads can complete `PTRACE_TRACEME` over the whole lifetime of the process: the checks of the
+//! following threads fail with [`DebugOffError::ThreadLimit`], and a new thread reusing the TID
+//! of a thread which completed `PTRACE_TRACEME` is considered as having completed it. The other
+//! checks, the watchdog and the host probe require the `std` feature.
+//!
 //! ## Usage Example
 //!
 //! In the example below, `debugoff` is used only when the target OS is Linux  and only for release
@@ -84,31 +136,244 @@
 //! println!("Example complete!");
 //! ```
 //!
+//! With the `macros` feature enabled, the same calls (including the `cfg` gating) can be inserted
+//! by the `protect` attribute:
+//!
+//! ```rust,ignore
+//! #[debugoff::protect(checks = "multi_ptrace,tracerpid", placement = "entry,exit")]
+//! fn main() {
+//!     println!("Example complete!");
+//! }
+//! ```
+//!
+//! The `protect_here!` macro inserts the checks at the call site, with the order of the checks,
+//! the loop counts and the constants of the `ptrace` call chains drawn for each call site (so
+//! that every protected location looks different):
+//!
+//! ```rust,ignore
+//! fn main() {
+//!     debugoff::protect_here!(checks = "multi_ptrace,tracerpid,parent");
+//!     println!("Example complete!");
+//! }
+//! ```
+//!
+//! Functions marked with the `critical` attribute are scanned for software breakpoints every time
+//! they are entered:
+//!
+//! ```rust,ignore
+//! #[debugoff::critical]
+//! fn check_license(key: &str) -> bool {
+//!     key == "secret"
+//! }
+//! ```
+//!
 
-use std::{error::Error, fmt};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+use core::fmt;
+use core::time::Duration;
 
 mod aa;
+// Raw system call wrappers and numbers for every arity, used or not by the checks.
+#[allow(dead_code)]
+#[cfg_attr(not(feature = "std"), allow(unused_imports))]
 mod arch;
+#[cfg(feature = "std")]
+mod breakpoint;
+mod checks;
+mod errno;
+#[cfg(feature = "std")]
+pub mod guardian;
+#[cfg(feature = "std")]
+mod harden;
+#[cfg(feature = "std")]
+mod injection;
+#[cfg(feature = "std")]
+pub mod integrity;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+#[doc(hidden)]
+pub mod obf;
+#[cfg(feature = "std")]
+mod parent;
+mod policy;
+#[cfg(feature = "std")]
+mod probe;
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod procfs;
+#[cfg(feature = "std")]
+mod sigtrap;
+mod sync;
+#[cfg(feature = "std")]
+pub mod timing;
+#[cfg(feature = "std")]
+mod tracerpid;
+#[cfg(feature = "std")]
+pub mod watchdog;
 
+#[doc(hidden)]
+pub use crate::aa::__multi_ptraceme_react;
+#[doc(hidden)]
+pub use crate::aa::__pick;
+#[doc(hidden)]
+pub use crate::aa::chain_check as __chain_check;
+#[doc(hidden)]
+pub use crate::aa::chain_ptraceme as __chain_ptraceme;
+pub use crate::aa::enable_global_state;
+pub use crate::aa::multi_ptraceme_checked;
 pub use crate::aa::multi_ptraceme_or_die;
+pub use crate::aa::ptraceme_checked;
 pub use crate::aa::ptraceme_or_die;
+#[cfg(feature = "std")]
+pub use crate::breakpoint::scan_breakpoints;
+#[cfg(feature = "std")]
+pub use crate::breakpoint::scan_breakpoints_or_die;
+pub use crate::checks::Check;
+pub use crate::errno::Errno;
+#[cfg(feature = "std")]
+pub use crate::harden::dumpable_checked;
+#[cfg(feature = "std")]
+pub use crate::harden::dumpable_or_die;
+#[cfg(feature = "std")]
+pub use crate::harden::harden_process;
+#[cfg(feature = "std")]
+pub use crate::harden::HardenConfig;
+#[cfg(feature = "std")]
+pub use crate::harden::HardenReport;
+#[cfg(feature = "std")]
+pub use crate::injection::allow_library;
+#[cfg(feature = "std")]
+pub use crate::injection::injection_checked;
+#[cfg(feature = "std")]
+pub use crate::injection::injection_or_die;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use crate::parent::__deny_parent;
+#[cfg(feature = "std")]
+pub use crate::parent::parent_checked;
+#[cfg(feature = "std")]
+pub use crate::parent::parent_or_die;
+pub use crate::policy::is_poisoned;
+pub use crate::policy::register_secret;
+pub use crate::policy::response_policy;
+pub use crate::policy::set_response_policy;
+pub use crate::policy::ResponsePolicy;
+#[cfg(feature = "std")]
+pub use crate::probe::capabilities;
+#[cfg(feature = "std")]
+pub use crate::probe::probe;
+#[cfg(feature = "std")]
+pub use crate::probe::Capabilities;
+#[cfg(feature = "std")]
+pub use crate::sigtrap::sigtrap_checked;
+#[cfg(feature = "std")]
+pub use crate::sigtrap::sigtrap_or_die;
+#[cfg(feature = "std")]
+pub use crate::tracerpid::tracerpid_checked;
+#[cfg(feature = "std")]
+pub use crate::tracerpid::tracerpid_or_die;
+#[cfg(feature = "macros")]
+pub use debugoff_macros::{critical, protect, protect_here};
 
-#[derive(Debug, Clone)]
-enum DebugOffErrType {
-    // InternalError,
+/// Reason why a check detected (or suspects) an analysis attempt.
+///
+/// Returned by the `*_checked` family of functions. New variants may be added as new checks are
+/// introduced, so matches on this type should always include a wildcard arm.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugOffError {
+    /// `ptrace(PTRACE_TRACEME, ...)` failed at the first invocation in the current thread: the
+    /// thread is already being traced.
     AlreadyTraced,
-    // ValError,
-}
-
-#[derive(Debug, Clone)]
-struct DebugOffErr {
-    err_type: DebugOffErrType,
+    /// The "`offset`" value computed along the `ptrace` call chain does not match the expected
+    /// one.
+    OffsetMismatch,
+    /// `ptrace(PTRACE_TRACEME, ...)` returned an unexpected value. The raw return value of the
+    /// system call is attached (e.g., 0 when the call succeeded more than once in the same
+    /// thread).
+    UnexpectedReturn(isize),
+    /// A system call used by a check failed with an unexpected error (e.g., `ENOSYS` returned by a
+    /// seccomp filter or by a kernel built without `ptrace` support).
+    Syscall(Errno),
+    /// A non-zero `TracerPid` has been found in `/proc`: the process (or one of its threads) is
+    /// traced by the attached PID.
+    TracerPid(u32),
+    /// A file under `/proc` could not be opened or read.
+    ProcAccess(Errno),
+    /// A file under `/proc` does not have the expected format.
+    ProcFormat,
+    /// The [watchdog](crate::watchdog) thread stopped running the checks.
+    WatchdogStalled,
+    /// A [timed region](crate::timing) took longer than the configured threshold (e.g., because
+    /// it was single-stepped or stopped at a breakpoint). The elapsed time is attached.
+    Timing(Duration),
+    /// Without the `std` feature, more than 64 threads completed `PTRACE_TRACEME` during the
+    /// lifetime of the process (see [`no_std`](crate#no_std)).
+    ThreadLimit,
+    /// The monotonic clock did not advance during the [timing](crate::timing) calibration.
+    ClockStalled,
+    /// A software breakpoint has been found in a scanned region of code. The offset of the
+    /// breakpoint from the start of the region is attached.
+    Breakpoint(usize),
+    /// The checksum of a region of code does not match the one stored in the
+    /// [seal](crate::integrity). The index of the region is attached.
+    Integrity(usize),
+    /// The [seal](crate::integrity) has not been written after linking.
+    NotSealed,
+    /// `LD_PRELOAD` or `LD_AUDIT` is set in the initial environment of the process.
+    PreloadVariable,
+    /// A shared object which has not been [allowed](crate::allow_library) is mapped as executable.
+    /// The address of the mapping is attached.
+    UnexpectedLibrary(usize),
+    /// Executable memory which is not backed by a file has been found. The address of the mapping
+    /// is attached.
+    AnonymousExecutable(usize),
+    /// The parent process is a debugger or another analysis tool (see
+    /// [`parent_checked`](crate::parent_checked)). The PID of the parent is attached.
+    Parent(u32),
+    /// The `SIGTRAP` raised by [`sigtrap_checked`](crate::sigtrap_checked) has not been delivered
+    /// to the process (e.g., it has been swallowed by a debugger).
+    SigTrap,
+    /// The [guardian](crate::guardian) process has not been started, terminated or stopped
+    /// tracing the process. Its PID is attached (0 if it has not been started).
+    GuardianLost(u32),
+    /// The process is dumpable: it has not been [hardened](crate::harden_process) or the
+    /// setting has been switched back on.
+    Dumpable,
 }
 
-impl Error for DebugOffErr {}
+#[cfg(feature = "std")]
+impl std::error::Error for DebugOffError {}
 
-impl fmt::Display for DebugOffErr {
+impl fmt::Display for DebugOffError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error: {:?}", self.err_type)
+        match self {
+            DebugOffError::AlreadyTraced => write!(f, "process is already traced"),
+            DebugOffError::OffsetMismatch => write!(f, "ptrace offset mismatch"),
+            DebugOffError::UnexpectedReturn(r) => {
+                write!(f, "unexpected ptrace return value: {}", r)
+            }
+            DebugOffError::TracerPid(pid) => write!(f, "traced by process {}", pid),
+            DebugOffError::Syscall(e) => write!(f, "unexpected system call error: {}", e),
+            DebugOffError::ProcAccess(e) => write!(f, "cannot access /proc: {}", e),
+            DebugOffError::ProcFormat => write!(f, "unexpected /proc file format"),
+            DebugOffError::WatchdogStalled => write!(f, "watchdog stalled"),
+            DebugOffError::Timing(d) => write!(f, "protected region took {:?}", d),
+            DebugOffError::ThreadLimit => write!(f, "too many threads completed PTRACE_TRACEME"),
+            DebugOffError::ClockStalled => write!(f, "monotonic clock stalled"),
+            DebugOffError::Breakpoint(o) => write!(f, "software breakpoint at offset {}", o),
+            DebugOffError::Integrity(i) => write!(f, "code region {} has been modified", i),
+            DebugOffError::NotSealed => write!(f, "integrity seal not written"),
+            DebugOffError::PreloadVariable => write!(f, "LD_PRELOAD or LD_AUDIT is set"),
+            DebugOffError::UnexpectedLibrary(a) => {
+                write!(f, "unexpected shared object mapped at {:#x}", a)
+            }
+            DebugOffError::AnonymousExecutable(a) => {
+                write!(f, "anonymous executable mapping at {:#x}", a)
+            }
+            DebugOffError::Parent(pid) => write!(f, "launched by analysis tool {}", pid),
+            DebugOffError::SigTrap => write!(f, "SIGTRAP intercepted"),
+            DebugOffError::GuardianLost(pid) => write!(f, "guardian process {} lost", pid),
+            DebugOffError::Dumpable => write!(f, "process is dumpable"),
+        }
     }
 }
@@ -0,0 +1,108 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Strings obfuscated at compile time.
+//
+// The strings matched by the checks (e.g., the names of the debuggers) would be an easy starting
+// point for the analysis of the binary if they were stored as plain literals. `__obf!` encodes a
+// literal at compile time with a key drawn at each build, so only the encoded bytes end up in the
+// binary. The strings are never decoded as a whole: they are compared one byte at a time.
+//
+// This module is public only for the macros exported by the crate.
+
+#[doc(hidden)]
+pub use const_random::const_random;
+
+use core::ptr;
+
+/// Obfuscated string.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct ObfStr {
+    data: &'static [u8],
+    key: u8,
+}
+
+/// Key used for the byte at `index`. The key is odd, so every byte is modified.
+const fn byte_key(key: u8, index: usize) -> u8 {
+    (key | 1).wrapping_mul((2 * index + 1) as u8)
+}
+
+/// Encode `s` with `key`.
+#[doc(hidden)]
+pub const fn encode<const N: usize>(s: &str, key: u8) -> [u8; N] {
+    let bytes = s.as_bytes();
+    let mut out = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        out[i] = bytes[i] ^ byte_key(key, i);
+        i += 1;
+    }
+    out
+}
+
+impl ObfStr {
+    #[doc(hidden)]
+    pub const fn new(data: &'static [u8], key: u8) -> ObfStr {
+        ObfStr { data, key }
+    }
+
+    /// Encode `s` at runtime (for the tests, where the strings are not known at compile time).
+    #[cfg(test)]
+    pub(crate) fn leak(s: &[u8], key: u8) -> ObfStr {
+        let data: Vec<u8> = s
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c ^ byte_key(key, i))
+            .collect();
+        ObfStr::new(Box::leak(data.into_boxed_slice()), key)
+    }
+
+    /// Length of the string.
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return `true` if `s` is equal to the first `max` bytes of the string (or to the whole
+    /// string if it is shorter).
+    pub(crate) fn matches(&self, s: &[u8], max: usize) -> bool {
+        let n = self.len().min(max);
+        // Volatile reads keep the optimizer from decoding the string at compile time.
+        let key = unsafe { ptr::read_volatile(&self.key) };
+        s.len() == n
+            && s.iter().enumerate().all(|(i, c)| {
+                let b = unsafe { ptr::read_volatile(&self.data[i]) };
+                b ^ byte_key(key, i) == *c
+            })
+    }
+}
+
+/// Obfuscate a string literal at compile time and return an `ObfStr`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __obf {
+    ($s:literal) => {{
+        const KEY: u8 = $crate::obf::const_random!(u8);
+        const DATA: [u8; $s.len()] = $crate::obf::encode($s, KEY);
+        $crate::obf::ObfStr::new(&DATA, KEY)
+    }};
+}
+
+#[cfg(test)]
+mod test {
+
+    #[test]
+    fn obfuscated() {
+        let s = crate::__obf!("strace");
+        assert_eq!(6, s.len());
+        assert!(s.matches(b"strace", usize::MAX));
+        assert!(!s.matches(b"strac", usize::MAX));
+        assert!(!s.matches(b"ltrace", usize::MAX));
+        assert!(s.matches(b"str", 3));
+        assert!(!s.matches(b"strace", 3));
+    }
+}
@@ -0,0 +1,197 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::obf::ObfStr;
+use crate::policy::the_end;
+use crate::procfs::{self, parse_u32, read_file, ProcPath};
+use crate::DebugOffError;
+use std::sync::Mutex;
+
+// `/proc/<pid>/comm` holds at most 15 characters.
+const COMM_LEN: usize = 15;
+
+// Maximum length of the path of the executable of the parent.
+const PATH_SIZE: usize = 512;
+
+// Programs launching the process under analysis.
+static DEFAULT_DENIED: [ObfStr; 10] = [
+    crate::__obf!("gdb"),
+    crate::__obf!("gdbserver"),
+    crate::__obf!("lldb"),
+    crate::__obf!("lldb-server"),
+    crate::__obf!("strace"),
+    crate::__obf!("ltrace"),
+    crate::__obf!("valgrind"),
+    crate::__obf!("rr"),
+    crate::__obf!("radare2"),
+    crate::__obf!("frida"),
+];
+
+static DENIED: Mutex<Vec<ObfStr>> = Mutex::new(Vec::new());
+
+#[doc(hidden)]
+pub fn __deny_parent(name: ObfStr) {
+    DENIED.lock().unwrap_or_else(|e| e.into_inner()).push(name);
+}
+
+/// Add a program to the list of the parents reported by [`parent_checked`].
+///
+/// The name is compared with the name of the parent process (`/proc/<ppid>/comm`, truncated to
+/// 15 characters by the kernel) and with the file name of its executable. It is obfuscated at
+/// compile time, so it is not stored as a plain string in the binary.
+///
+/// ## Examples
+///
+/// ```rust
+/// debugoff::deny_parent!("ida64");
+/// debugoff::deny_parent!("edb");
+/// ```
+#[macro_export]
+macro_rules! deny_parent {
+    ($name:literal) => {
+        $crate::__deny_parent($crate::__obf!($name))
+    };
+}
+
+/// Return the PPID field of `/proc/<pid>/stat`.
+fn stat_ppid(stat: &[u8]) -> Option<u32> {
+    // The name of the process (second field) is in parentheses and can contain any character.
+    let end = stat.iter().rposition(|c| *c == b')')?;
+    let mut fields = stat[end + 1..]
+        .split(|c| *c == b' ')
+        .filter(|f| !f.is_empty());
+    let _state = fields.next()?;
+    parse_u32(fields.next()?)
+}
+
+/// Return `true` if `comm` or the file name of `exe` is one of the `denied` names.
+fn is_denied(comm: &[u8], exe: &[u8], denied: &[ObfStr]) -> bool {
+    let exe = exe.strip_suffix(b" (deleted)").unwrap_or(exe);
+    let file = exe.rsplit(|c| *c == b'/').next().unwrap_or(exe);
+    denied
+        .iter()
+        .any(|n| n.matches(comm, COMM_LEN) || (!file.is_empty() && n.matches(file, usize::MAX)))
+}
+
+/// Check the process `ppid` against `denied`.
+fn check_parent(ppid: u32, denied: &[ObfStr]) -> Result<(), DebugOffError> {
+    let mut comm = [0u8; COMM_LEN + 1];
+    let mut path = ProcPath::new();
+    path.push(b"/proc/").push_u32(ppid).push(b"/comm");
+    let n = read_file(path.as_bytes(), &mut comm)?;
+    let comm = comm[..n].strip_suffix(b"\n").unwrap_or(&comm[..n]);
+
+    // The executable of a process owned by another user cannot be read: only the name is checked.
+    let mut exe = [0u8; PATH_SIZE];
+    let mut path = ProcPath::new();
+    path.push(b"/proc/").push_u32(ppid).push(b"/exe");
+    let len = procfs::read_link(path.as_bytes(), &mut exe).unwrap_or(0);
+
+    if is_denied(comm, &exe[..len], denied) {
+        return Err(DebugOffError::Parent(ppid));
+    }
+    Ok(())
+}
+
+/// Check whether the parent process is a debugger or another analysis tool.
+///
+/// The PPID is read from `/proc/self/stat` and the name (`/proc/<ppid>/comm`) and executable
+/// (`/proc/<ppid>/exe`) of the parent are compared with a list of programs (`gdb`, `lldb`,
+/// `lldb-server`, `strace`, `ltrace`, `valgrind`, ...) which can be extended with
+/// [`deny_parent!`](crate::deny_parent). Files are opened and read with raw system calls and the
+/// names are stored obfuscated in the binary.
+///
+/// If the parent is one of the listed programs, the function returns
+/// [`DebugOffError::Parent`](crate::DebugOffError::Parent) with the PID of the parent.
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::parent_checked() {
+///     eprintln!("Launched by an analysis tool: {}", e);
+/// }
+/// ```
+#[inline(always)]
+pub fn parent_checked() -> Result<(), DebugOffError> {
+    let mut stat = [0u8; 1024];
+    let n = read_file(b"/proc/self/stat\0", &mut stat)?;
+    let ppid = stat_ppid(&stat[..n]).ok_or(DebugOffError::ProcFormat)?;
+
+    let mut denied = DENIED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    denied.extend_from_slice(&DEFAULT_DENIED);
+    check_parent(ppid, &denied)
+}
+
+/// Check whether the parent process is a debugger or another analysis tool.
+///
+/// This is a wrapper around [`parent_checked`]: if the parent is one of the listed programs (or
+/// the check cannot be performed), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::parent_or_die();
+/// ```
+#[inline(always)]
+pub fn parent_or_die() {
+    if parent_checked().is_err() && crate::probe::is_supported(crate::Check::Parent) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{check_parent, is_denied, stat_ppid, DEFAULT_DENIED};
+    use crate::obf::ObfStr;
+    use crate::DebugOffError;
+
+    #[test]
+    fn stat() {
+        assert_eq!(Some(1), stat_ppid(b"42 (app) S 1 42 42 0 -1 4194304"));
+        assert_eq!(Some(7), stat_ppid(b"42 (a) b) (c) R 7 42 42 0 -1"));
+        assert_eq!(None, stat_ppid(b"42 (app"));
+    }
+
+    #[test]
+    fn denied() {
+        assert!(is_denied(b"gdb", b"", &DEFAULT_DENIED));
+        assert!(is_denied(b"bash", b"/usr/bin/strace", &DEFAULT_DENIED));
+        assert!(is_denied(
+            b"x",
+            b"/usr/bin/valgrind (deleted)",
+            &DEFAULT_DENIED
+        ));
+        assert!(!is_denied(b"bash", b"/usr/bin/bash", &DEFAULT_DENIED));
+        assert!(!is_denied(b"gdbus", b"/usr/bin/gdbus", &DEFAULT_DENIED));
+
+        // `comm` is truncated to 15 characters.
+        let long = [crate::__obf!("analysis-tool-x64")];
+        assert!(is_denied(b"analysis-tool-x", b"", &long));
+        assert!(is_denied(b"sh", b"/opt/analysis-tool-x64", &long));
+    }
+
+    #[test]
+    fn parent() {
+        let ppid = std::os::unix::process::parent_id();
+        assert_eq!(Ok(()), check_parent(ppid, &DEFAULT_DENIED));
+
+        // Deny the actual parent of the test process.
+        let comm = std::fs::read(format!("/proc/{}/comm", ppid)).unwrap();
+        let denied = [ObfStr::leak(comm.strip_suffix(b"\n").unwrap(), 0x5A)];
+        assert_eq!(
+            Err(DebugOffError::Parent(ppid)),
+            check_parent(ppid, &denied)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::sync::SpinLock;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "obfuscate")]
+use goldberg::*;
+
+#[cfg(not(feature = "std"))]
+const SIGABRT: i32 = 6;
+
+/// What the `*_or_die` functions do when a check fails.
+///
+/// The policy is configured once for the whole process with [`set_response_policy`] and is
+/// honoured by every `*_or_die` function of the crate. When no policy is configured,
+/// [`ResponsePolicy::Exit(0)`](ResponsePolicy::Exit) is used.
+///
+/// Policies that do not terminate the process ([`ResponsePolicy::Signal`] with a handled signal,
+/// [`ResponsePolicy::CorruptSecret`], [`ResponsePolicy::Callback`] and [`ResponsePolicy::Poison`])
+/// let the execution continue after the failed check.
+#[derive(Debug, Clone, Copy)]
+pub enum ResponsePolicy {
+    /// Terminate all the threads of the process with `exit_group(code)`.
+    Exit(i32),
+    /// Send the given signal to the calling thread, as with `raise(sig)`.
+    Signal(i32),
+    /// Abort the process, as with `std::process::abort` (without the `std` feature, `SIGABRT` is
+    /// raised and the process exits with status 134 if the signal is handled).
+    Abort,
+    /// Overwrite the secret registered with [`register_secret`] with random bytes.
+    CorruptSecret,
+    /// Call a user provided function.
+    Callback(fn()),
+    /// Set the poisoned flag that can be queried with [`is_poisoned`].
+    Poison,
+}
+
+impl Default for ResponsePolicy {
+    fn default() -> Self {
+        ResponsePolicy::Exit(0)
+    }
+}
+
+static POLICY: SpinLock<Option<ResponsePolicy>> = SpinLock::new(None);
+static POISONED: AtomicBool = AtomicBool::new(false);
+// Address and length of the secret registered with `register_secret`.
+static SECRET: SpinLock<Option<(usize, usize)>> = SpinLock::new(None);
+
+/// Configure the response policy for the whole process.
+///
+/// The policy can be set only once, ideally at startup before any check is executed. If a policy
+/// has already been configured, the one passed as argument is returned as error.
+///
+/// ## Examples
+///
+/// ```rust
+/// use debugoff::ResponsePolicy;
+///
+/// debugoff::set_response_policy(ResponsePolicy::Exit(137)).unwrap();
+/// assert!(debugoff::set_response_policy(ResponsePolicy::Abort).is_err());
+/// ```
+pub fn set_response_policy(policy: ResponsePolicy) -> Result<(), ResponsePolicy> {
+    let mut p = POLICY.lock();
+    if p.is_some() {
+        return Err(policy);
+    }
+    *p = Some(policy);
+    Ok(())
+}
+
+/// Return the response policy currently in use.
+pub fn response_policy() -> ResponsePolicy {
+    let p = *POLICY.lock();
+    p.unwrap_or_default()
+}
+
+/// Register the secret overwritten by [`ResponsePolicy::CorruptSecret`].
+///
+/// A secret registered with a previous call is replaced (and left untouched).
+///
+/// # Safety
+///
+/// `secret` must point to `len` writable bytes that stay valid for the rest of the process
+/// lifetime (or until another secret is registered). The caller must ensure that the bytes are
+/// not accessed concurrently while a failed check overwrites them.
+///
+/// ## Examples
+///
+/// ```rust
+/// static mut KEY: [u8; 16] = [0x42; 16];
+///
+/// unsafe { debugoff::register_secret(core::ptr::addr_of_mut!(KEY) as *mut u8, 16) };
+/// ```
+pub unsafe fn register_secret(secret: *mut u8, len: usize) {
+    *SECRET.lock() = Some((secret as usize, len));
+}
+
+/// Return `true` if a check failed while [`ResponsePolicy::Poison`] was in use.
+pub fn is_poisoned() -> bool {
+    POISONED.load(Ordering::SeqCst)
+}
+
+/// React to a failed check according to the configured [`ResponsePolicy`].
+#[inline(always)]
+pub(crate) fn the_end() {
+    match response_policy() {
+        ResponsePolicy::Exit(code) => exit_group(code),
+        ResponsePolicy::Signal(sig) => raise(sig),
+        ResponsePolicy::Abort => abort(),
+        ResponsePolicy::CorruptSecret => corrupt_secret(),
+        ResponsePolicy::Callback(f) => f(),
+        ResponsePolicy::Poison => POISONED.store(true, Ordering::SeqCst),
+    }
+}
+
+#[cfg(feature = "obfuscate")]
+#[inline(always)]
+fn exit_group(code: i32) {
+    // Be carefull, optimizer in release mode can decide to remove the following code.
+    // let p: *mut u32 = core::ptr::null_mut();
+    // unsafe {
+    //     *p = 0xFF;
+    // }
+
+    let _res = unsafe {
+        crate::arch::syscall1(
+            crate::arch::SysNo::SYS_EXIT_GROUP,
+            (code as usize) ^ goldberg_int!(0usize),
+        )
+    };
+}
+#[cfg(not(feature = "obfuscate"))]
+#[inline(always)]
+fn exit_group(code: i32) {
+    // Be carefull, optimizer in release mode can decide to remove the following code.
+    // let p: *mut u32 = core::ptr::null_mut();
+    // unsafe {
+    //     *p = 0xFF;
+    // }
+
+    let _res = unsafe { crate::arch::syscall1(crate::arch::SysNo::SYS_EXIT_GROUP, code as usize) };
+}
+
+#[inline(always)]
+fn raise(sig: i32) {
+    let _res = unsafe {
+        let pid = crate::arch::syscall0(crate::arch::SysNo::SYS_GETPID).unwrap_or(0);
+        let tid = crate::arch::syscall0(crate::arch::SysNo::SYS_GETTID).unwrap_or(0);
+        crate::arch::syscall3(crate::arch::SysNo::SYS_TGKILL, pid, tid, sig as usize)
+    };
+}
+
+#[cfg(feature = "std")]
+#[inline(always)]
+fn abort() {
+    std::process::abort()
+}
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn abort() {
+    // Same exit status as a process killed by SIGABRT, if the signal is handled or ignored.
+    raise(SIGABRT);
+    exit_group(134);
+}
+
+fn corrupt_secret() {
+    let s = *SECRET.lock();
+    if let Some((ptr, len)) = s {
+        // Safety: guaranteed by the contract of `register_secret`.
+        let secret = unsafe { core::slice::from_raw_parts_mut(ptr as *mut u8, len) };
+        for chunk in secret.chunks_mut(4) {
+            let r = crate::aa::rand().to_ne_bytes();
+            chunk.copy_from_slice(&r[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::ResponsePolicy;
+    use crate::aa::test::{run_in_child, trace_current_thread};
+
+    #[test]
+    fn poison() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                super::set_response_policy(ResponsePolicy::Poison).unwrap();
+                let before = super::is_poisoned();
+                trace_current_thread();
+                crate::ptraceme_or_die();
+                !before && super::is_poisoned()
+            })
+        );
+    }
+
+    #[test]
+    fn corrupt_secret() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let secret: &'static mut [u8] = Box::leak(Box::new([0u8; 33]));
+                let ptr = secret.as_mut_ptr();
+                unsafe { super::register_secret(ptr, secret.len()) };
+                super::set_response_policy(ResponsePolicy::CorruptSecret).unwrap();
+                trace_current_thread();
+                crate::ptraceme_or_die();
+                let secret = unsafe { std::slice::from_raw_parts(ptr, 33) };
+                secret.iter().any(|b| *b != 0)
+            })
+        );
+    }
+
+    #[test]
+    fn exit_code() {
+        assert_eq!(
+            3,
+            run_in_child(|| {
+                super::set_response_policy(ResponsePolicy::Exit(3)).unwrap();
+                trace_current_thread();
+                crate::ptraceme_or_die();
+                true
+            })
+        );
+    }
+}
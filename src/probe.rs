@@ -0,0 +1,334 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::arch::{syscall4, SysNo, SIGSYS};
+use crate::errno::Errno;
+use crate::guardian::{exit_group, fork, wait4};
+use crate::procfs::{parse_u32, read_file};
+use crate::tracerpid::status_field;
+use crate::{Check, DebugOffError};
+use std::sync::Mutex;
+
+// Capabilities of the host, recorded by the last call to `probe`.
+static PROBED: Mutex<Option<Capabilities>> = Mutex::new(None);
+
+/// Features of the host required by the checks, as reported by [`probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Raw system calls return the same results as the C library (the PID returned by a raw
+    /// `getpid` matches [`std::process::id`]). Reported for information only: the C library can
+    /// be hooked (e.g., with `LD_PRELOAD`), so a mismatch does not disable any check.
+    pub raw_syscalls: bool,
+    /// `ptrace(PTRACE_TRACEME, ...)` succeeds in a child process. The error is `ENOSYS` if the
+    /// child has been killed with `SIGSYS` (by a seccomp filter). Failures of a traced child
+    /// (e.g., under `strace -f` or a debugger following forks) and children killed by any other
+    /// signal are not reported.
+    pub ptrace: Result<(), Errno>,
+    /// Value of `/proc/sys/kernel/yama/ptrace_scope` (`None` if the Yama LSM is not enabled).
+    pub ptrace_scope: Option<u32>,
+    /// Seccomp mode of the process, from `/proc/self/status` (0: disabled, 1: strict, 2:
+    /// filter; `None` if unknown).
+    pub seccomp: Option<u32>,
+    /// `/proc/self/status` can be read.
+    pub procfs: Result<(), Errno>,
+}
+
+impl Capabilities {
+    /// Return `true` if `check` can run reliably on the host, i.e., if its failures can only be
+    /// caused by an analysis attempt.
+    pub fn supports(&self, check: Check) -> bool {
+        match check {
+            Check::Ptrace | Check::MultiPtrace | Check::Timing => self.ptrace_usable(),
+            // The guardian attaches to its parent, which Yama forbids from `ptrace_scope` 2.
+            Check::Guardian => {
+                self.ptrace_usable() && self.procfs.is_ok() && self.ptrace_scope.unwrap_or(0) < 2
+            }
+            Check::TracerPid | Check::Injection | Check::Parent => self.procfs.is_ok(),
+            Check::Integrity | Check::SigTrap | Check::Dumpable => true,
+        }
+    }
+
+    /// Return `false` if `ptrace` is forbidden by the host: it is not implemented (or the probe
+    /// has been killed with `SIGSYS` by a seccomp filter), or it fails with `EPERM` under a
+    /// seccomp filter or with Yama `ptrace_scope` 3. Any other failure may be caused by a
+    /// debugger.
+    fn ptrace_usable(&self) -> bool {
+        match self.ptrace {
+            Err(Errno::ENOSYS) => false,
+            Err(Errno::EPERM) => self.seccomp.unwrap_or(0) == 0 && self.ptrace_scope != Some(3),
+            _ => true,
+        }
+    }
+}
+
+/// Run `PTRACE_TRACEME` in a child process, which exits with the error number.
+///
+/// An error is returned only if the child is not traced: a tracer following the forks of the
+/// process can make `PTRACE_TRACEME` fail without any limitation of the host.
+fn probe_ptrace() -> Result<(), Errno> {
+    let child = match fork()? {
+        0 => {
+            let res = unsafe { syscall4(SysNo::SYS_PTRACE, 0, 0, 0, 0) };
+            let code = match res {
+                Err(_) if status_field(b"/proc/self/status\0", b"TracerPid") != Ok(0) => 0,
+                Err(e) => e.raw() as usize,
+                Ok(_) => 0,
+            };
+            exit_group(code)
+        }
+        child => child as i32,
+    };
+
+    let mut status = 0i32;
+    while let Err(e) = wait4(child, &mut status, 0) {
+        if e != Errno::EINTR {
+            return Err(e);
+        }
+    }
+    child_result(status)
+}
+
+/// Decode the wait status of the child of [`probe_ptrace`].
+///
+/// Only `SIGSYS` (raised by a seccomp filter killing the child) is reported as `ENOSYS`: a child
+/// killed by any other signal may have been killed by a debugger following the forks.
+fn child_result(status: i32) -> Result<(), Errno> {
+    match (status & 0x7F, (status >> 8) & 0xFF) {
+        (0, 0) => Ok(()),
+        (0, errno) => Err(Errno::new(errno)),
+        (sig, _) if sig as usize == SIGSYS => Err(Errno::ENOSYS),
+        _ => Ok(()),
+    }
+}
+
+/// Test which checks can run reliably on the host.
+///
+/// Some environments make the checks fail even when no debugger is present: in containers or
+/// under seccomp filters `ptrace` may fail with `EPERM` or be forbidden, `/proc` may not be
+/// mounted, and sandboxes may intercept system calls. The probe tests the raw system call layer
+/// (a raw `getpid` against [`std::process::id`]), runs `PTRACE_TRACEME` in a short-lived child
+/// process, reads the Yama `ptrace_scope` and the seccomp mode from `/proc/self/status`. The probe
+/// does not detect debuggers and never reacts to its results.
+///
+/// Only failures that a debugger cannot cause disable a check: `ptrace` returning `ENOSYS`, or
+/// `EPERM` when a seccomp filter or Yama `ptrace_scope` 3 is in place, and `/proc/self/status`
+/// not being readable.
+///
+/// The returned capabilities are recorded for the whole process: from then on, the `*_or_die`
+/// functions and the [watchdog](crate::watchdog) do not react to the failures of the checks that
+/// are not [supported](Capabilities::supports) on the host (the `*_checked` functions still
+/// report them). The probe should therefore be called at startup, before any check is executed
+/// and before a debugger can interfere with it.
+///
+/// ## Examples
+///
+/// ```rust
+/// use debugoff::Check;
+///
+/// let caps = debugoff::probe();
+/// if !caps.supports(Check::Ptrace) {
+///     eprintln!("ptrace is not usable here: {:?}", caps.ptrace);
+/// }
+///
+/// // Does not terminate the process when `ptrace` is forbidden by the host.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::ptraceme_or_die();
+/// ```
+pub fn probe() -> Capabilities {
+    let raw_syscalls = crate::aa::getpid() == std::process::id();
+
+    let mut buf = [0u8; 16];
+    let ptrace_scope = read_file(b"/proc/sys/kernel/yama/ptrace_scope\0", &mut buf)
+        .ok()
+        .and_then(|n| parse_u32(buf[..n].strip_suffix(b"\n").unwrap_or(&buf[..n])));
+
+    let (procfs, seccomp) = match status_field(b"/proc/self/status\0", b"Seccomp") {
+        Ok(mode) => (Ok(()), Some(mode)),
+        Err(DebugOffError::ProcAccess(e)) => (Err(e), None),
+        // Kernels built without seccomp support do not report the mode.
+        Err(_) => (Ok(()), None),
+    };
+
+    let caps = Capabilities {
+        raw_syscalls,
+        ptrace: probe_ptrace(),
+        ptrace_scope,
+        seccomp,
+        procfs,
+    };
+    *PROBED.lock().unwrap_or_else(|e| e.into_inner()) = Some(caps);
+    caps
+}
+
+/// Return the capabilities recorded by the last call to [`probe`].
+pub fn capabilities() -> Option<Capabilities> {
+    *PROBED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Return `false` if the host has been probed and `check` is not supported: its failures must
+/// not be reacted to.
+pub(crate) fn is_supported(check: Check) -> bool {
+    match capabilities() {
+        Some(caps) => caps.supports(check),
+        None => true,
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::{child_result, probe, probe_ptrace, Capabilities};
+    use crate::aa::test::run_in_child;
+    use crate::errno::Errno;
+    use crate::{Check, ResponsePolicy};
+
+    #[test]
+    fn host() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let caps = probe();
+                caps.raw_syscalls
+                    && caps.ptrace.is_ok()
+                    && caps.procfs.is_ok()
+                    && caps.seccomp.is_some()
+                    && super::capabilities() == Some(caps)
+                    && [Check::Ptrace, Check::TracerPid, Check::SigTrap]
+                        .iter()
+                        .all(|c| caps.supports(*c))
+            })
+        );
+    }
+
+    #[test]
+    fn traced() {
+        // The probe does not depend on the tracer of the process.
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::aa::test::trace_current_thread();
+                probe_ptrace().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn killed_child() {
+        // A child killed by a debugger is not a limitation of the host.
+        let status = unsafe {
+            let child = libc::fork();
+            if child == 0 {
+                libc::raise(libc::SIGKILL);
+                libc::_exit(1);
+            }
+            let mut status = 0;
+            assert_eq!(child, libc::waitpid(child, &mut status, 0));
+            status
+        };
+        assert!(libc::WIFSIGNALED(status) && libc::WTERMSIG(status) == libc::SIGKILL);
+        assert_eq!(Ok(()), child_result(status));
+
+        // Killed by a seccomp filter.
+        assert_eq!(Err(Errno::ENOSYS), child_result(libc::SIGSYS));
+        assert_eq!(Err(Errno::EPERM), child_result(1 << 8));
+        assert_eq!(Ok(()), child_result(0));
+    }
+
+    #[test]
+    fn unsupported() {
+        let caps = Capabilities {
+            raw_syscalls: true,
+            ptrace: Err(Errno::EPERM),
+            ptrace_scope: Some(3),
+            seccomp: Some(2),
+            procfs: Ok(()),
+        };
+        assert!(!caps.supports(Check::Ptrace));
+        assert!(!caps.supports(Check::Guardian));
+        assert!(caps.supports(Check::TracerPid));
+
+        let caps = Capabilities {
+            ptrace: Err(Errno::ENOSYS),
+            ptrace_scope: None,
+            seccomp: Some(0),
+            ..caps
+        };
+        assert!(!caps.supports(Check::Ptrace));
+    }
+
+    #[test]
+    fn spoofed() {
+        // EPERM without a seccomp filter or Yama scope 3 can be caused by a tracer.
+        let caps = Capabilities {
+            raw_syscalls: true,
+            ptrace: Err(Errno::EPERM),
+            ptrace_scope: Some(1),
+            seccomp: Some(0),
+            procfs: Ok(()),
+        };
+        assert!(caps.supports(Check::Ptrace));
+        assert!(caps.supports(Check::Guardian));
+
+        // A hooked C library does not disable any check.
+        let caps = Capabilities {
+            raw_syscalls: false,
+            ptrace: Ok(()),
+            ..caps
+        };
+        assert!([Check::Ptrace, Check::TracerPid, Check::Integrity]
+            .iter()
+            .all(|c| caps.supports(*c)));
+    }
+
+    #[test]
+    fn guardian_scope() {
+        let caps = Capabilities {
+            raw_syscalls: true,
+            ptrace: Ok(()),
+            ptrace_scope: Some(2),
+            seccomp: Some(0),
+            procfs: Ok(()),
+        };
+        assert!(caps.supports(Check::Ptrace));
+        assert!(!caps.supports(Check::Guardian));
+        assert!(Capabilities {
+            ptrace_scope: Some(1),
+            ..caps
+        }
+        .supports(Check::Guardian));
+        assert!(Capabilities {
+            ptrace_scope: None,
+            ..caps
+        }
+        .supports(Check::Guardian));
+    }
+
+    #[test]
+    fn downgraded() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::set_response_policy(ResponsePolicy::Poison).unwrap();
+                probe();
+                *super::PROBED.lock().unwrap() = Some(Capabilities {
+                    ptrace: Err(Errno::EPERM),
+                    seccomp: Some(2),
+                    ..super::capabilities().unwrap()
+                });
+
+                // `PTRACE_TRACEME` fails, as when forbidden by a seccomp filter.
+                crate::aa::test::trace_current_thread();
+                crate::ptraceme_or_die();
+                let downgraded = !crate::is_poisoned();
+                crate::tracerpid_or_die();
+                downgraded && crate::is_poisoned()
+            })
+        );
+    }
+}
@@ -0,0 +1,335 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Minimal access to procfs built on top of the raw system calls in `crate::arch` (libc is never
+// used, so hooking libc file functions does not affect the checks reading procfs).
+//
+// Only fixed-size buffers are used: the files read by the checks are small and the caller
+// provides the storage.
+
+use crate::arch::{syscall1, syscall3, syscall4, SysNo};
+use crate::errno::{Errno, SyscallResult};
+use crate::DebugOffError;
+
+const AT_FDCWD: isize = -100;
+const O_RDONLY: usize = 0;
+const O_CLOEXEC: usize = 0o2000000;
+const SEEK_SET: usize = 0;
+
+// Offsets inside `struct linux_dirent64`.
+const DIRENT_RECLEN: usize = 16;
+const DIRENT_NAME: usize = 19;
+
+/// File descriptor opened with a raw `openat` and closed on drop.
+pub(crate) struct RawFile {
+    fd: usize,
+}
+
+impl RawFile {
+    /// Open `path` (which must be NUL terminated) read-only.
+    pub(crate) fn open(path: &[u8]) -> Result<RawFile, Errno> {
+        debug_assert_eq!(path.last(), Some(&0));
+        let res = unsafe {
+            syscall3(
+                SysNo::SYS_OPENAT,
+                AT_FDCWD as usize,
+                path.as_ptr() as usize,
+                O_RDONLY | O_CLOEXEC,
+            )
+        };
+        res.map(|fd| RawFile { fd })
+    }
+
+    /// Read from the file into `buf` with a single `read` system call.
+    pub(crate) fn read(&self, buf: &mut [u8]) -> SyscallResult {
+        unsafe {
+            syscall3(
+                SysNo::SYS_READ,
+                self.fd,
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+            )
+        }
+    }
+
+    /// Read from the file until `buf` is full or the end of the file is reached.
+    pub(crate) fn read_all(&self, buf: &mut [u8]) -> SyscallResult {
+        let mut len = 0;
+        while len < buf.len() {
+            match self.read(&mut buf[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(len)
+    }
+
+    /// Move the file offset to `offset` bytes from the start of the file.
+    pub(crate) fn seek(&self, offset: usize) -> SyscallResult {
+        unsafe { syscall3(SysNo::SYS_LSEEK, self.fd, offset, SEEK_SET) }
+    }
+
+    /// Read directory entries into `buf` with a single `getdents64` system call.
+    fn getdents(&self, buf: &mut [u8]) -> SyscallResult {
+        unsafe {
+            syscall3(
+                SysNo::SYS_GETDENTS64,
+                self.fd,
+                buf.as_mut_ptr() as usize,
+                buf.len(),
+            )
+        }
+    }
+}
+
+impl Drop for RawFile {
+    fn drop(&mut self) {
+        let _res = unsafe { syscall1(SysNo::SYS_CLOSE, self.fd) };
+    }
+}
+
+/// Read the file at `path` (NUL terminated) into `buf` and return the number of bytes read.
+///
+/// Files larger than `buf` are truncated.
+pub(crate) fn read_file(path: &[u8], buf: &mut [u8]) -> Result<usize, DebugOffError> {
+    let f = RawFile::open(path).map_err(DebugOffError::ProcAccess)?;
+    f.read_all(buf).map_err(DebugOffError::ProcAccess)
+}
+
+/// Call `f` with the name of each entry of the directory at `path` (NUL terminated), skipping `.`
+/// and `..`.
+pub(crate) fn for_each_entry<F>(path: &[u8], mut f: F) -> Result<(), DebugOffError>
+where
+    F: FnMut(&[u8]) -> Result<(), DebugOffError>,
+{
+    let dir = RawFile::open(path).map_err(DebugOffError::ProcAccess)?;
+    let mut buf = [0u8; 1024];
+
+    loop {
+        let n = dir.getdents(&mut buf).map_err(DebugOffError::ProcAccess)?;
+        if n == 0 {
+            return Ok(());
+        }
+
+        let mut pos = 0;
+        while pos + DIRENT_NAME < n {
+            let reclen =
+                u16::from_ne_bytes([buf[pos + DIRENT_RECLEN], buf[pos + DIRENT_RECLEN + 1]])
+                    as usize;
+            if reclen == 0 || pos + reclen > n {
+                return Err(DebugOffError::ProcFormat);
+            }
+            let name = &buf[pos + DIRENT_NAME..pos + reclen];
+            let name = &name[..name.iter().position(|c| *c == 0).unwrap_or(name.len())];
+            if name != b"." && name != b".." {
+                f(name)?;
+            }
+            pos += reclen;
+        }
+    }
+}
+
+/// Call `f` with each line of the file at `path` (NUL terminated), without the trailing newline.
+///
+/// Lines longer than the internal buffer (1 KiB) are truncated.
+pub(crate) fn for_each_line<F>(path: &[u8], f: F) -> Result<(), DebugOffError>
+where
+    F: FnMut(&[u8]) -> Result<(), DebugOffError>,
+{
+    for_each_record(path, b'\n', f)
+}
+
+/// Call `f` with each record of the file at `path` (NUL terminated), where records are terminated
+/// by `sep` (e.g., `\0` in `/proc/self/environ`). The separator is not passed to `f`.
+///
+/// Records longer than the internal buffer (1 KiB) are truncated.
+pub(crate) fn for_each_record<F>(path: &[u8], sep: u8, mut f: F) -> Result<(), DebugOffError>
+where
+    F: FnMut(&[u8]) -> Result<(), DebugOffError>,
+{
+    let file = RawFile::open(path).map_err(DebugOffError::ProcAccess)?;
+    let mut buf = [0u8; 1024];
+    let mut len = 0;
+    // Set while skipping the remaining part of a truncated record.
+    let mut skip = false;
+
+    loop {
+        let n = file
+            .read(&mut buf[len..])
+            .map_err(DebugOffError::ProcAccess)?;
+        if n == 0 {
+            if len > 0 && !skip {
+                f(&buf[..len])?;
+            }
+            return Ok(());
+        }
+        len += n;
+
+        let mut start = 0;
+        while let Some(end) = buf[start..len].iter().position(|c| *c == sep) {
+            if !skip {
+                f(&buf[start..start + end])?;
+            }
+            skip = false;
+            start += end + 1;
+        }
+
+        if start == 0 && len == buf.len() {
+            if !skip {
+                f(&buf[..len])?;
+            }
+            skip = true;
+            len = 0;
+        } else {
+            buf.copy_within(start..len, 0);
+            len -= start;
+        }
+    }
+}
+
+/// Read the target of the symbolic link at `path` (NUL terminated) into `buf` and return its
+/// length. Targets longer than `buf` are truncated.
+pub(crate) fn read_link(path: &[u8], buf: &mut [u8]) -> Result<usize, DebugOffError> {
+    debug_assert_eq!(path.last(), Some(&0));
+    let res = unsafe {
+        syscall4(
+            SysNo::SYS_READLINKAT,
+            AT_FDCWD as usize,
+            path.as_ptr() as usize,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+        )
+    };
+    res.map_err(DebugOffError::ProcAccess)
+}
+
+/// Return `true` if `err` reports a file that does not exist (e.g., a thread that exited between
+/// the listing of `/proc/self/task` and the opening of its files).
+pub(crate) fn is_not_found(err: &DebugOffError) -> bool {
+    *err == DebugOffError::ProcAccess(Errno::ENOENT)
+}
+
+/// Return the value of field `name` in a `Name:\tvalue` formatted file (e.g.,
+/// `/proc/self/status`), without leading and trailing white spaces.
+pub(crate) fn find_field<'a>(content: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    content.split(|c| *c == b'\n').find_map(|line| {
+        let value = line.strip_prefix(name)?.strip_prefix(b":")?;
+        let start = value.iter().position(|c| !c.is_ascii_whitespace())?;
+        let end = value.iter().rposition(|c| !c.is_ascii_whitespace())?;
+        Some(&value[start..=end])
+    })
+}
+
+/// Parse an unsigned decimal number.
+pub(crate) fn parse_u32(s: &[u8]) -> Option<u32> {
+    if s.is_empty() {
+        return None;
+    }
+    s.iter().try_fold(0u32, |acc, c| match c {
+        b'0'..=b'9' => acc.checked_mul(10)?.checked_add((c - b'0') as u32),
+        _ => None,
+    })
+}
+
+/// Parse an unsigned hexadecimal number (without `0x` prefix).
+pub(crate) fn parse_hex(s: &[u8]) -> Option<usize> {
+    if s.is_empty() {
+        return None;
+    }
+    s.iter().try_fold(0usize, |acc, c| {
+        let digit = (*c as char).to_digit(16)?;
+        acc.checked_mul(16)?.checked_add(digit as usize)
+    })
+}
+
+/// Line of `/proc/<pid>/maps`.
+pub(crate) struct Mapping<'a> {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) perms: &'a [u8],
+    pub(crate) offset: usize,
+    /// Path of the mapped file, pseudo-path (e.g., `[vdso]`) or empty for anonymous mappings.
+    pub(crate) path: &'a [u8],
+}
+
+impl Mapping<'_> {
+    /// Return `true` if the mapping is executable.
+    pub(crate) fn is_exec(&self) -> bool {
+        self.perms.get(2) == Some(&b'x')
+    }
+}
+
+/// Parse a line of `/proc/<pid>/maps`:
+///
+/// `<start>-<end> <perms> <offset> <dev> <inode> <path>`
+pub(crate) fn parse_mapping(line: &[u8]) -> Option<Mapping<'_>> {
+    let mut fields = line.split(|c| *c == b' ').filter(|f| !f.is_empty());
+    let (start, end) = {
+        let mut range = fields.next()?.split(|c| *c == b'-');
+        (parse_hex(range.next()?)?, parse_hex(range.next()?)?)
+    };
+    let perms = fields.next()?;
+    let offset = parse_hex(fields.next()?)?;
+    let _dev = fields.next()?;
+    let _inode = fields.next()?;
+    // The path may contain spaces: take everything up to the end of the line.
+    let path = match fields.next() {
+        Some(p) => &line[p.as_ptr() as usize - line.as_ptr() as usize..],
+        None => &[],
+    };
+    Some(Mapping {
+        start,
+        end,
+        perms,
+        offset,
+        path,
+    })
+}
+
+/// NUL terminated path built in a fixed-size buffer.
+pub(crate) struct ProcPath {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl ProcPath {
+    pub(crate) fn new() -> ProcPath {
+        ProcPath {
+            buf: [0u8; 64],
+            len: 0,
+        }
+    }
+
+    /// Append `s` to the path. Bytes that do not fit in the buffer are discarded.
+    pub(crate) fn push(&mut self, s: &[u8]) -> &mut ProcPath {
+        // The last byte is reserved for the terminating NUL.
+        let n = s.len().min(self.buf.len() - 1 - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s[..n]);
+        self.len += n;
+        self
+    }
+
+    /// Append the decimal representation of `n` to the path.
+    pub(crate) fn push_u32(&mut self, mut n: u32) -> &mut ProcPath {
+        let mut digits = [0u8; 10];
+        let mut i = digits.len();
+        loop {
+            i -= 1;
+            digits[i] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        self.push(&digits[i..])
+    }
+
+    /// Return the path including the terminating NUL.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buf[..=self.len]
+    }
+}
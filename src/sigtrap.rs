@@ -0,0 +1,208 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::arch::{
+    skip_trap, syscall4, trap, SigAction, SigSet, SysNo, SIGTRAP, SIG_SETMASK, SIG_UNBLOCK,
+};
+use crate::policy::the_end;
+use crate::DebugOffError;
+use core::mem;
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+// Key combining the challenge into the token written by the handler.
+const KEY: u32 = const_random::const_random!(u32);
+
+// Only one check at a time can own the SIGTRAP handler.
+static LOCK: Mutex<()> = Mutex::new(());
+
+static CHALLENGE: AtomicU32 = AtomicU32::new(0);
+static TOKEN: AtomicU32 = AtomicU32::new(0);
+
+/// Value written by the handler for `challenge`.
+#[inline(always)]
+fn answer(challenge: u32) -> u32 {
+    challenge.rotate_left(13) ^ KEY
+}
+
+extern "C" fn handler(_sig: i32, _info: *mut u8, uc: *mut u8) {
+    TOKEN.store(answer(CHALLENGE.load(Ordering::SeqCst)), Ordering::SeqCst);
+    unsafe { skip_trap(uc) };
+}
+
+unsafe fn sigaction(new: *const SigAction, old: *mut SigAction) -> Result<(), DebugOffError> {
+    syscall4(
+        SysNo::SYS_RT_SIGACTION,
+        SIGTRAP,
+        new as usize,
+        old as usize,
+        mem::size_of::<SigSet>(),
+    )
+    .map(|_| ())
+    .map_err(DebugOffError::Syscall)
+}
+
+unsafe fn sigprocmask(
+    how: usize,
+    set: *const SigSet,
+    old: *mut SigSet,
+) -> Result<(), DebugOffError> {
+    syscall4(
+        SysNo::SYS_RT_SIGPROCMASK,
+        how,
+        set as usize,
+        old as usize,
+        mem::size_of::<SigSet>(),
+    )
+    .map(|_| ())
+    .map_err(DebugOffError::Syscall)
+}
+
+/// Raise `SIGTRAP` with the handler installed and the signal unblocked.
+unsafe fn raise_trap() -> Result<(), DebugOffError> {
+    let mut unblock: SigSet = mem::zeroed();
+    unblock[0] = 1 << (SIGTRAP - 1);
+    let mut mask: SigSet = mem::zeroed();
+    sigprocmask(SIG_UNBLOCK, &unblock, &mut mask)?;
+    trap();
+    sigprocmask(SIG_SETMASK, &mask, core::ptr::null_mut())
+}
+
+/// Check whether `SIGTRAP` is delivered to the process.
+///
+/// A handler for `SIGTRAP` is installed with a raw `rt_sigaction` system call, then a breakpoint
+/// instruction of the target architecture is executed. The handler answers a random challenge
+/// drawn for each call: a debugger which intercepts the trap and does not deliver the signal
+/// (e.g., gdb and most other tracers by default) leaves the answer unwritten. The previous handler
+/// and signal mask are restored before returning.
+///
+/// If the signal has not been delivered, the function returns
+/// [`DebugOffError::SigTrap`](crate::DebugOffError::SigTrap). On architectures where the trap does
+/// not advance the program counter, a debugger which keeps swallowing the signal stops the process
+/// on the same instruction forever.
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::sigtrap_checked() {
+///     eprintln!("Debugger detected: {}", e);
+/// }
+/// ```
+#[inline(never)]
+pub fn sigtrap_checked() -> Result<(), DebugOffError> {
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let challenge = crate::aa::rand();
+    CHALLENGE.store(challenge, Ordering::SeqCst);
+    TOKEN.store(!answer(challenge), Ordering::SeqCst);
+
+    let new = SigAction::new(handler as extern "C" fn(i32, *mut u8, *mut u8) as usize);
+    let mut old = SigAction::empty();
+    unsafe {
+        sigaction(&new, &mut old)?;
+        let res = raise_trap();
+        sigaction(&old, core::ptr::null_mut())?;
+        res?;
+    }
+
+    if TOKEN.load(Ordering::SeqCst) != answer(challenge) {
+        return Err(DebugOffError::SigTrap);
+    }
+    Ok(())
+}
+
+/// Check whether `SIGTRAP` is delivered to the process.
+///
+/// This is a wrapper around [`sigtrap_checked`]: if the signal is intercepted (or the check cannot
+/// be performed), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::sigtrap_or_die();
+/// ```
+#[inline(always)]
+pub fn sigtrap_or_die() {
+    if sigtrap_checked().is_err() && crate::probe::is_supported(crate::Check::SigTrap) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::sigtrap_checked;
+    use crate::aa::test::{run_in_child, trace_current_thread};
+    use crate::DebugOffError;
+
+    extern "C" fn previous(_sig: libc::c_int) {}
+
+    fn handler() -> libc::sighandler_t {
+        unsafe {
+            let mut act: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(libc::SIGTRAP, std::ptr::null(), &mut act);
+            act.sa_sigaction
+        }
+    }
+
+    #[test]
+    fn delivered() {
+        assert_eq!(Ok(()), sigtrap_checked());
+        assert_eq!(Ok(()), sigtrap_checked());
+    }
+
+    #[test]
+    fn restored() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                libc::signal(
+                    libc::SIGTRAP,
+                    previous as extern "C" fn(libc::c_int) as libc::sighandler_t,
+                );
+                sigtrap_checked() == Ok(())
+                    && handler() == previous as extern "C" fn(libc::c_int) as libc::sighandler_t
+            })
+        );
+    }
+
+    #[test]
+    fn blocked() {
+        assert_eq!(
+            0,
+            run_in_child(|| unsafe {
+                let mut set: libc::sigset_t = std::mem::zeroed();
+                libc::sigemptyset(&mut set);
+                libc::sigaddset(&mut set, libc::SIGTRAP);
+                libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+                let ok = sigtrap_checked() == Ok(());
+
+                let mut mask: libc::sigset_t = std::mem::zeroed();
+                libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(), &mut mask);
+                ok && libc::sigismember(&mask, libc::SIGTRAP) == 1
+            })
+        );
+    }
+
+    #[test]
+    fn swallowed() {
+        // The tracer of `run_in_child` does not deliver SIGTRAP to the child.
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                trace_current_thread();
+                sigtrap_checked() == Err(DebugOffError::SigTrap)
+            })
+        );
+    }
+}
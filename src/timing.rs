@@ -0,0 +1,293 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Timing checks.
+//!
+//! Single-stepping a region of code or stopping at a breakpoint inside it makes the region take
+//! orders of magnitude longer than a normal execution. A [`Region`] measures the time elapsed
+//! since its start and reports [`DebugOffError::Timing`](crate::DebugOffError::Timing) when it
+//! exceeds the configured threshold (100 ms by default, see [`set_threshold`]).
+//!
+//! Time is measured with the cycle counter of the CPU where it can be read from user space
+//! (`rdtsc` on `x86`/`x86_64`, `cntvct_el0` on `aarch64` and `rdtime` on `riscv64`) and with a raw
+//! `clock_gettime` system call elsewhere. The frequency of the counter is measured by a short
+//! calibration phase (about 10 ms), which should be run at startup with [`calibrate`]; otherwise
+//! it is run the first time a region is started. If the calibration fails, the checks of the
+//! regions report the error.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use debugoff::timing::{self, Region};
+//!
+//! timing::calibrate().unwrap();
+//!
+//! let region = Region::start();
+//! // ...
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! region.check_or_die();
+//!
+//! // Run multi_ptraceme_or_die and fail if it was single-stepped.
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! timing::timed_or_die(debugoff::multi_ptraceme_or_die);
+//! ```
+
+use crate::arch::{syscall2, SysNo};
+use crate::policy::the_end;
+use crate::{DebugOffError, Errno};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const CLOCK_MONOTONIC: usize = 1;
+
+// Length of the calibration phase.
+const CALIBRATION_NS: u64 = 10_000_000;
+// Maximum number of clock reads of the calibration phase (far more than needed for 10 ms).
+const CALIBRATION_MAX_READS: u32 = 10_000_000;
+
+// Counter ticks per millisecond (0 until calibrated).
+static TICKS_PER_MS: AtomicU64 = AtomicU64::new(0);
+// Maximum duration of a region, in nanoseconds.
+static THRESHOLD_NS: AtomicU64 = AtomicU64::new(100_000_000);
+
+/// Read the monotonic clock with a raw `clock_gettime` system call.
+fn monotonic_ns() -> Result<u64, Errno> {
+    // `struct timespec` made of two `long`.
+    let mut ts = [0isize; 2];
+    unsafe {
+        syscall2(
+            SysNo::SYS_CLOCK_GETTIME,
+            CLOCK_MONOTONIC,
+            ts.as_mut_ptr() as usize,
+        )?
+    };
+    Ok((ts[0] as u64)
+        .wrapping_mul(1_000_000_000)
+        .wrapping_add(ts[1] as u64))
+}
+
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+))]
+#[inline(always)]
+fn counter() -> u64 {
+    crate::arch::read_counter()
+}
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+#[inline(always)]
+fn counter() -> u64 {
+    // A failing clock is reported by the calibration.
+    monotonic_ns().unwrap_or(0)
+}
+
+/// Measure the frequency of the counter used to time the regions.
+///
+/// The calibration takes about 10 ms and should be run once at startup. Running it again is
+/// harmless.
+///
+/// Returns [`DebugOffError::Syscall`](crate::DebugOffError::Syscall) if the monotonic clock cannot
+/// be read and [`DebugOffError::ClockStalled`](crate::DebugOffError::ClockStalled) if it does not
+/// advance.
+pub fn calibrate() -> Result<(), DebugOffError> {
+    let ticks = calibrate_with(monotonic_ns, counter)?;
+    TICKS_PER_MS.store(ticks, Ordering::Release);
+    Ok(())
+}
+
+/// Return the ticks of `counter` per millisecond of `clock` (in nanoseconds).
+fn calibrate_with<C, F>(mut clock: C, mut counter: F) -> Result<u64, DebugOffError>
+where
+    C: FnMut() -> Result<u64, Errno>,
+    F: FnMut() -> u64,
+{
+    let t0 = clock().map_err(DebugOffError::Syscall)?;
+    let c0 = counter();
+    let mut t1 = t0;
+    let mut reads = 0;
+    while t1.wrapping_sub(t0) < CALIBRATION_NS {
+        if reads == CALIBRATION_MAX_READS {
+            return Err(DebugOffError::ClockStalled);
+        }
+        t1 = clock().map_err(DebugOffError::Syscall)?;
+        reads += 1;
+    }
+    let c1 = counter();
+
+    let ticks = c1.saturating_sub(c0) as u128 * 1_000_000 / t1.wrapping_sub(t0) as u128;
+    Ok((ticks as u64).max(1))
+}
+
+fn ticks_per_ms() -> Result<u64, DebugOffError> {
+    match TICKS_PER_MS.load(Ordering::Acquire) {
+        0 => {
+            calibrate()?;
+            Ok(TICKS_PER_MS.load(Ordering::Acquire))
+        }
+        t => Ok(t),
+    }
+}
+
+/// Set the maximum time a region can take before it is reported as analyzed.
+///
+/// The threshold must be large enough to tolerate the preemption of the thread running the
+/// region. The default value is 100 ms.
+pub fn set_threshold(threshold: Duration) {
+    THRESHOLD_NS.store(threshold.as_nanos() as u64, Ordering::Release);
+}
+
+/// Return the maximum time a region can take before it is reported as analyzed.
+pub fn threshold() -> Duration {
+    Duration::from_nanos(THRESHOLD_NS.load(Ordering::Acquire))
+}
+
+/// Region of code whose execution time is checked.
+#[derive(Debug, Clone)]
+pub struct Region {
+    start: u64,
+    // Error of the calibration, reported by the checks.
+    ticks_per_ms: Result<u64, DebugOffError>,
+}
+
+impl Region {
+    /// Start a region (calibrating the counter if [`calibrate`] has not been called yet).
+    #[inline(always)]
+    pub fn start() -> Region {
+        let ticks_per_ms = ticks_per_ms();
+        Region {
+            start: counter(),
+            ticks_per_ms,
+        }
+    }
+
+    /// Time elapsed since the start of the region ([`Duration::MAX`] if the counter could not be
+    /// calibrated).
+    #[inline(always)]
+    pub fn elapsed(&self) -> Duration {
+        let ticks_per_ms = match self.ticks_per_ms {
+            Ok(t) => t,
+            Err(_) => return Duration::MAX,
+        };
+        // The counter can go backward when the thread migrates to a CPU whose counter is not
+        // synchronized.
+        let ticks = counter().saturating_sub(self.start) as u128;
+        Duration::from_nanos((ticks * 1_000_000 / ticks_per_ms as u128) as u64)
+    }
+
+    /// Check the time elapsed since the start of the region.
+    ///
+    /// Returns [`DebugOffError::Timing`](crate::DebugOffError::Timing) with the elapsed time if it
+    /// exceeds the [threshold](set_threshold), or the error of the calibration if it failed.
+    #[inline(always)]
+    pub fn check(&self) -> Result<(), DebugOffError> {
+        self.ticks_per_ms.clone()?;
+        let elapsed = self.elapsed();
+        if elapsed > threshold() {
+            return Err(DebugOffError::Timing(elapsed));
+        }
+        Ok(())
+    }
+
+    /// Check the time elapsed since the start of the region.
+    ///
+    /// This is a wrapper around [`Region::check`]: if the check fails, the function reacts
+    /// according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+    /// `exit_group(0)`).
+    #[inline(always)]
+    pub fn check_or_die(&self) {
+        if self.check().is_err() {
+            the_end();
+        }
+    }
+}
+
+/// Run `f` in a [`Region`] and return its result, or
+/// [`DebugOffError::Timing`](crate::DebugOffError::Timing) if it took longer than the
+/// [threshold](set_threshold).
+#[inline(always)]
+pub fn timed_checked<R, F: FnOnce() -> R>(f: F) -> Result<R, DebugOffError> {
+    let region = Region::start();
+    let res = f();
+    region.check()?;
+    Ok(res)
+}
+
+/// Run `f` in a [`Region`] and return its result.
+///
+/// This is a wrapper around [`timed_checked`]: if `f` took longer than the
+/// [threshold](set_threshold), the function reacts according to the configured
+/// [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls `exit_group(0)`).
+#[inline(always)]
+pub fn timed_or_die<R, F: FnOnce() -> R>(f: F) -> R {
+    let region = Region::start();
+    let res = f();
+    region.check_or_die();
+    res
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::Region;
+    use crate::aa::test::run_in_child;
+    use crate::{DebugOffError, Errno};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn calibration() {
+        super::calibrate().unwrap();
+        let region = Region::start();
+        thread::sleep(Duration::from_millis(20));
+        let elapsed = region.elapsed();
+        assert!(elapsed >= Duration::from_millis(15), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn slow_region() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                super::set_threshold(Duration::from_millis(20));
+                let fast = super::timed_checked(|| 1 + 1);
+                let slow = super::timed_checked(|| thread::sleep(Duration::from_millis(100)));
+                fast == Ok(2) && matches!(slow, Err(DebugOffError::Timing(_)))
+            })
+        );
+    }
+
+    #[test]
+    fn calibration_errors() {
+        assert_eq!(
+            Err(DebugOffError::ClockStalled),
+            super::calibrate_with(|| Ok(42), || 0)
+        );
+        assert_eq!(
+            Err(DebugOffError::Syscall(Errno::ENOSYS)),
+            super::calibrate_with(|| Err(Errno::ENOSYS), || 0)
+        );
+        // Counter at 1 GHz.
+        let now = std::cell::Cell::new(0);
+        let clock = || {
+            now.set(now.get() + 1_000_000);
+            Ok(now.get())
+        };
+        assert_eq!(Ok(1_000_000), super::calibrate_with(clock, || now.get()));
+    }
+}
@@ -0,0 +1,164 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+use crate::policy::the_end;
+use crate::procfs::{find_field, for_each_entry, is_not_found, parse_u32, read_file, ProcPath};
+use crate::DebugOffError;
+
+// `/proc/<pid>/status` is usually smaller than 2KB and `TracerPid` is one of the first fields.
+const STATUS_SIZE: usize = 4096;
+
+/// Return the value of the numeric field `name` in the status file at `path` (NUL terminated).
+pub(crate) fn status_field(path: &[u8], name: &[u8]) -> Result<u32, DebugOffError> {
+    let mut buf = [0u8; STATUS_SIZE];
+    let n = read_file(path, &mut buf)?;
+
+    find_field(&buf[..n], name)
+        .and_then(parse_u32)
+        .ok_or(DebugOffError::ProcFormat)
+}
+
+/// Return `true` if the thread `tracer` belongs to the parent process.
+fn is_parent_thread(tracer: u32) -> Result<bool, DebugOffError> {
+    let ppid = status_field(b"/proc/self/status\0", b"PPid")?;
+
+    let mut path = ProcPath::new();
+    path.push(b"/proc/").push_u32(tracer).push(b"/status");
+    Ok(status_field(path.as_bytes(), b"Tgid")? == ppid)
+}
+
+/// Check the status file at `path` (NUL terminated) of thread `tid`.
+///
+/// `PTRACE_TRACEME` makes the parent process the tracer of the calling thread: the tracer of a
+/// thread which completed `PTRACE_TRACEME` through the ptrace checks is accepted if it is a
+/// thread of the parent process (any other tracer would have made `PTRACE_TRACEME` fail).
+#[inline(always)]
+fn check_status(path: &[u8], tid: u32) -> Result<(), DebugOffError> {
+    match status_field(path, b"TracerPid")? {
+        0 => Ok(()),
+        pid if crate::guardian::is_guardian(pid) => Ok(()),
+        pid if crate::aa::self_traced(tid) && is_parent_thread(pid)? => Ok(()),
+        pid => Err(DebugOffError::TracerPid(pid)),
+    }
+}
+
+/// Look for a tracer in `/proc/self/status` and in `/proc/self/task/<tid>/status` for every
+/// thread of the process.
+///
+/// Differently from [`ptraceme_checked`](crate::ptraceme_checked), this check also detects a
+/// tracer that attached to the process (or to any of its threads) after the first
+/// `ptrace(PTRACE_TRACEME, ...)` call. Files are opened and read with raw system calls.
+///
+/// If a non-zero `TracerPid` is found, the function returns
+/// [`DebugOffError::TracerPid`](crate::DebugOffError::TracerPid) with the PID of the tracer. The
+/// parent process, which becomes the tracer of the threads that completed the ptrace checks, is
+/// not reported for these threads, nor is the [guardian](crate::guardian).
+///
+/// ## Examples
+///
+/// ```rust
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// if let Err(e) = debugoff::tracerpid_checked() {
+///     println!("Debugger detected: {}", e);
+/// }
+/// ```
+#[inline(always)]
+pub fn tracerpid_checked() -> Result<(), DebugOffError> {
+    // The status of the process is the status of its main thread.
+    check_status(b"/proc/self/status\0", crate::aa::getpid())?;
+
+    for_each_entry(b"/proc/self/task\0", |tid| {
+        let mut path = ProcPath::new();
+        path.push(b"/proc/self/task/").push(tid).push(b"/status");
+        match check_status(path.as_bytes(), parse_u32(tid).unwrap_or(0)) {
+            Err(e) if is_not_found(&e) => Ok(()),
+            res => res,
+        }
+    })
+}
+
+/// Look for a tracer in `/proc/self/status` and in `/proc/self/task/<tid>/status` for every
+/// thread of the process.
+///
+/// This is a wrapper around [`tracerpid_checked`]: if the check fails, the function reacts
+/// according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default, it calls
+/// `exit_group(0)`).
+///
+/// ## Examples
+///
+/// ```rust
+/// // Call only on Linux and for "release" builds.
+/// #[cfg(target_os = "linux")]
+/// #[cfg(not(debug_assertions))]
+/// debugoff::tracerpid_or_die();
+/// ```
+#[inline(always)]
+pub fn tracerpid_or_die() {
+    if tracerpid_checked().is_err() && crate::probe::is_supported(crate::Check::TracerPid) {
+        the_end();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use crate::aa::test::{run_in_child, trace_current_thread};
+    use crate::DebugOffError;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn tracerpid_process() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let before = super::tracerpid_checked();
+                trace_current_thread();
+                before.is_ok()
+                    && matches!(super::tracerpid_checked(), Err(DebugOffError::TracerPid(_)))
+            })
+        );
+    }
+
+    #[test]
+    fn tracerpid_self_traced() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::ptraceme_checked().is_ok()
+                    && crate::multi_ptraceme_checked().is_ok()
+                    && super::tracerpid_checked().is_ok()
+            })
+        );
+    }
+
+    #[test]
+    fn tracerpid_thread() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let (traced_tx, traced_rx) = mpsc::channel();
+                let (done_tx, done_rx) = mpsc::channel::<()>();
+                let t = thread::spawn(move || {
+                    trace_current_thread();
+                    traced_tx.send(()).unwrap();
+                    done_rx.recv().unwrap();
+                });
+
+                traced_rx.recv().unwrap();
+                let res = super::tracerpid_checked();
+                done_tx.send(()).unwrap();
+                t.join().unwrap();
+
+                matches!(res, Err(DebugOffError::TracerPid(_)))
+            })
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+//! Background thread that periodically re-runs a set of checks.
+//!
+//! Calling the checks only at a few points of a program leaves long windows in which a tracer
+//! attached to the process goes unnoticed. The watchdog closes these windows by running the
+//! configured checks on a jittered interval (randomized at each iteration, so that the timing is
+//! not predictable). Failed checks are handled with the configured
+//! [`ResponsePolicy`](crate::ResponsePolicy).
+//!
+//! Each round of checks updates a heartbeat that the other threads can verify with
+//! [`Watchdog::verify`]: if the watchdog thread is killed, suspended or stuck, the heartbeat stops
+//! and the verification fails.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use debugoff::watchdog::{self, WatchdogConfig};
+//! use debugoff::Check;
+//! use std::time::Duration;
+//!
+//! let wd = watchdog::spawn(
+//!     WatchdogConfig::new()
+//!         .interval(Duration::from_millis(200))
+//!         .jitter(Duration::from_millis(100))
+//!         .checks(&[Check::TracerPid]),
+//! );
+//!
+//! // ...
+//!
+//! #[cfg(target_os = "linux")]
+//! #[cfg(not(debug_assertions))]
+//! wd.verify_or_die();
+//!
+//! wd.stop();
+//! ```
+
+use crate::policy::the_end;
+use crate::{Check, DebugOffError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Configuration of the watchdog thread.
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    interval: Duration,
+    jitter: Duration,
+    checks: Vec<Check>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            interval: Duration::from_secs(1),
+            jitter: Duration::from_millis(500),
+            checks: vec![Check::TracerPid],
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Default configuration: [`Check::TracerPid`] every second, with up to 500 ms of jitter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimum time between two rounds of checks.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Maximum random time added to `interval` at each round.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Checks executed at each round, in order.
+    pub fn checks(mut self, checks: &[Check]) -> Self {
+        self.checks = checks.to_vec();
+        self
+    }
+
+    /// Longest time expected between two heartbeats.
+    fn max_period(&self) -> Duration {
+        // Leave some room for scheduling delays and for the time spent running the checks.
+        (self.interval + self.jitter) * 2 + Duration::from_millis(100)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    start: Instant,
+    stop: AtomicBool,
+    // Milliseconds elapsed from `start` at the last completed round of checks.
+    heartbeat: AtomicU64,
+}
+
+/// Handle to a running watchdog thread.
+///
+/// Dropping the handle detaches the thread, which keeps running until the process exits.
+#[derive(Debug)]
+pub struct Watchdog {
+    shared: Arc<Shared>,
+    max_period: Duration,
+    thread: JoinHandle<()>,
+}
+
+/// Start a watchdog thread configured with `config`.
+pub fn spawn(config: WatchdogConfig) -> Watchdog {
+    let shared = Arc::new(Shared {
+        start: Instant::now(),
+        stop: AtomicBool::new(false),
+        heartbeat: AtomicU64::new(0),
+    });
+    let max_period = config.max_period();
+
+    let s = shared.clone();
+    let thread = thread::spawn(move || run(config, s));
+
+    Watchdog {
+        shared,
+        max_period,
+        thread,
+    }
+}
+
+fn run(config: WatchdogConfig, shared: Arc<Shared>) {
+    let jitter_ms = config.jitter.as_millis() as u64;
+
+    while !shared.stop.load(Ordering::SeqCst) {
+        for check in config.checks.iter() {
+            if check.run().is_err() && crate::probe::is_supported(*check) {
+                the_end();
+            }
+        }
+
+        shared
+            .heartbeat
+            .store(shared.start.elapsed().as_millis() as u64, Ordering::SeqCst);
+
+        let jitter = match jitter_ms {
+            0 => 0,
+            j => crate::aa::rand() as u64 % (j + 1),
+        };
+        thread::park_timeout(config.interval + Duration::from_millis(jitter));
+    }
+}
+
+impl Watchdog {
+    /// Verify that the watchdog thread is alive and running the checks.
+    ///
+    /// Returns [`DebugOffError::WatchdogStalled`](crate::DebugOffError::WatchdogStalled) if the
+    /// thread terminated or if the last heartbeat is older than twice the maximum configured
+    /// period (interval plus jitter).
+    pub fn verify(&self) -> Result<(), DebugOffError> {
+        let now = self.shared.start.elapsed().as_millis() as u64;
+        let last = self.shared.heartbeat.load(Ordering::SeqCst);
+
+        if self.thread.is_finished()
+            || now.saturating_sub(last) > self.max_period.as_millis() as u64
+        {
+            return Err(DebugOffError::WatchdogStalled);
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the watchdog thread is alive and running the checks.
+    ///
+    /// This is a wrapper around [`Watchdog::verify`]: if the verification fails, the function
+    /// reacts according to the configured [`ResponsePolicy`](crate::ResponsePolicy) (by default,
+    /// it calls `exit_group(0)`).
+    pub fn verify_or_die(&self) {
+        if self.verify().is_err() {
+            the_end();
+        }
+    }
+
+    /// Stop the watchdog thread and wait for its termination.
+    pub fn stop(self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        self.thread.thread().unpark();
+        let _ = self.thread.join();
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(test)]
+mod test {
+
+    use super::WatchdogConfig;
+    use crate::aa::test::{run_in_child, trace_current_thread};
+    use crate::{Check, ResponsePolicy};
+    use std::thread;
+    use std::time::Duration;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig::new()
+            .interval(Duration::from_millis(10))
+            .jitter(Duration::from_millis(10))
+            .checks(&[Check::TracerPid])
+    }
+
+    #[test]
+    fn heartbeat() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                let wd = super::spawn(config());
+                thread::sleep(Duration::from_millis(100));
+                let ok = wd.verify().is_ok();
+                wd.stop();
+                ok
+            })
+        );
+    }
+
+    #[test]
+    fn detection() {
+        assert_eq!(
+            0,
+            run_in_child(|| {
+                crate::set_response_policy(ResponsePolicy::Poison).unwrap();
+                let wd = super::spawn(config());
+                thread::sleep(Duration::from_millis(50));
+                let before = crate::is_poisoned();
+                trace_current_thread();
+                thread::sleep(Duration::from_millis(100));
+                wd.stop();
+                !before && crate::is_poisoned()
+            })
+        );
+    }
+}
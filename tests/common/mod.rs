@@ -0,0 +1,221 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Helpers shared by the integration tests: build the examples with a set of features and run
+// them, optionally under a tracer.
+
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::FromRawFd;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Serializes builds and runs: `run` reaps any child (traced threads of the example are reported
+// to the tracer with their own TID), so runs must not overlap.
+static LOCK: Mutex<()> = Mutex::new(());
+
+// Time after which a traced example is considered stuck and killed.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Optional features the examples are built with (`std` and `macros` are always enabled).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    pub obfuscate: bool,
+    pub syscallobf: bool,
+}
+
+/// Every combination of the optional features.
+pub const FEATURE_SETS: [Features; 4] = [
+    Features {
+        obfuscate: false,
+        syscallobf: false,
+    },
+    Features {
+        obfuscate: true,
+        syscallobf: false,
+    },
+    Features {
+        obfuscate: false,
+        syscallobf: true,
+    },
+    Features {
+        obfuscate: true,
+        syscallobf: true,
+    },
+];
+
+impl Features {
+    /// Features enabled for the current test.
+    pub fn current() -> Features {
+        Features {
+            obfuscate: cfg!(feature = "obfuscate"),
+            syscallobf: cfg!(feature = "syscallobf"),
+        }
+    }
+
+    fn list(&self) -> Vec<&'static str> {
+        let mut features = vec!["std", "macros"];
+        if self.obfuscate {
+            features.push("obfuscate");
+        }
+        if self.syscallobf {
+            features.push("syscallobf");
+        }
+        features
+    }
+}
+
+/// Build all the examples with the features enabled for the current test and return the
+/// directory containing the binaries.
+pub fn build_examples(release: bool) -> PathBuf {
+    build_examples_with(release, Features::current())
+}
+
+/// Build all the examples with `features` and return the directory containing the binaries.
+///
+/// Every feature set has its own target directory, so that binaries built with other features are
+/// never picked up.
+pub fn build_examples_with(release: bool, features: Features) -> PathBuf {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let list = features.list();
+    let target_dir =
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("examples-{}", list.join("-")));
+
+    let mut cmd = Command::new(env!("CARGO"));
+    cmd.current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["build", "--examples", "--no-default-features"])
+        .args(["--features", &list.join(",")])
+        .arg("--target-dir")
+        .arg(&target_dir);
+    if release {
+        cmd.arg("--release");
+    }
+    assert!(cmd.status().expect("failed to run cargo").success());
+
+    target_dir
+        .join(if release { "release" } else { "debug" })
+        .join("examples")
+}
+
+/// How the example is traced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tracer {
+    /// Not traced.
+    None,
+    /// The child calls `PTRACE_TRACEME` before `execv` (like a debugger starting a program).
+    TraceMe,
+    /// The test attaches to the child with `PTRACE_ATTACH` before `execv` (like a debugger
+    /// attaching to a running program).
+    Attach,
+}
+
+/// Exit status and standard output of an example.
+#[derive(Debug)]
+pub struct Outcome {
+    /// Exit code, or 128 + signal number if the example was killed by a signal.
+    pub status: i32,
+    pub stdout: String,
+}
+
+/// Run `binary` with `tracer` and wait for its termination.
+///
+/// Signals stopping the traced threads are forwarded (except for the `SIGTRAP` and `SIGSTOP`
+/// generated by `execv` and `PTRACE_ATTACH`), so that the example runs as it would under a
+/// debugger which just continues its execution.
+pub fn run(binary: &Path, tracer: Tracer) -> Outcome {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // Everything needed by the child is prepared before fork().
+    let path = CString::new(binary.as_os_str().as_bytes()).unwrap();
+    let argv = [path.as_ptr(), std::ptr::null()];
+
+    unsafe {
+        let mut out = [0; 2];
+        let mut sync = [0; 2];
+        assert_eq!(0, libc::pipe2(out.as_mut_ptr(), libc::O_CLOEXEC));
+        assert_eq!(0, libc::pipe2(sync.as_mut_ptr(), libc::O_CLOEXEC));
+
+        let child = libc::fork();
+        assert!(child >= 0);
+        if child == 0 {
+            libc::dup2(out[1], libc::STDOUT_FILENO);
+            // The write end must be closed in the child too, otherwise `read` never sees EOF.
+            libc::close(sync[1]);
+            match tracer {
+                Tracer::None => {}
+                Tracer::TraceMe => {
+                    libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0);
+                }
+                Tracer::Attach => {
+                    // Wait for the parent to attach (EOF when it closes its end).
+                    let mut b = 0u8;
+                    libc::read(sync[0], &mut b as *mut u8 as *mut libc::c_void, 1);
+                }
+            }
+            libc::execv(path.as_ptr(), argv.as_ptr());
+            libc::_exit(127);
+        }
+
+        libc::close(out[1]);
+        libc::close(sync[0]);
+
+        if tracer == Tracer::Attach {
+            assert_eq!(0, libc::ptrace(libc::PTRACE_ATTACH, child, 0, 0));
+        }
+        libc::close(sync[1]);
+
+        let status = wait_child(child);
+
+        let mut stdout = String::new();
+        File::from_raw_fd(out[0])
+            .read_to_string(&mut stdout)
+            .unwrap();
+
+        Outcome { status, stdout }
+    }
+}
+
+unsafe fn wait_child(child: libc::pid_t) -> i32 {
+    let deadline = Instant::now() + TIMEOUT;
+
+    loop {
+        let mut status: libc::c_int = 0;
+        let pid = libc::waitpid(-1, &mut status, libc::__WALL | libc::WNOHANG);
+        assert!(pid >= 0);
+
+        if pid == 0 {
+            if Instant::now() > deadline {
+                libc::kill(child, libc::SIGKILL);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            continue;
+        }
+
+        if libc::WIFSTOPPED(status) {
+            let sig = match libc::WSTOPSIG(status) {
+                libc::SIGTRAP | libc::SIGSTOP => 0,
+                sig => sig,
+            };
+            libc::ptrace(libc::PTRACE_CONT, pid, 0, sig);
+            continue;
+        }
+
+        if pid == child {
+            if libc::WIFSIGNALED(status) {
+                return 128 + libc::WTERMSIG(status);
+            }
+            return libc::WEXITSTATUS(status);
+        }
+    }
+}
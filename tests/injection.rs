@@ -0,0 +1,56 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Run an example with and without `LD_PRELOAD`.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::build_examples;
+use std::fs;
+use std::process::Command;
+
+/// Path of the C library loaded by the current process.
+fn libc_path() -> String {
+    let maps = fs::read_to_string("/proc/self/maps").unwrap();
+    maps.lines()
+        .filter_map(|l| l.split_whitespace().nth(5))
+        .find(|p| p.contains("/libc.so") || p.contains("/libc-"))
+        .expect("libc not found")
+        .to_string()
+}
+
+fn check_example(release: bool) {
+    let binary = build_examples(release).join("simple_injection");
+
+    let out = Command::new(&binary)
+        .env_remove("LD_PRELOAD")
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Example complete!"));
+
+    // Preloading a library which is loaded anyway is harmless, but the variable is detected.
+    let out = Command::new(&binary)
+        .env("LD_PRELOAD", libc_path())
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(!release, stdout.contains("Example complete!"), "{:?}", out);
+}
+
+#[test]
+fn debug_profile() {
+    check_example(false);
+}
+
+#[test]
+fn release_profile() {
+    check_example(true);
+}
@@ -0,0 +1,75 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Seal a release (stripped) example with `debugoff-seal` and check that it runs only when the
+// seal matches the binary.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{build_examples, run, Tracer};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DONE: &[u8] = b"Example complete!";
+
+/// Build `debugoff-seal` and return the path of the binary.
+fn build_seal() -> PathBuf {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("seal");
+    let status = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["build", "-p", "debugoff-seal", "--target-dir"])
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success());
+    target_dir.join("debug").join("debugoff-seal")
+}
+
+fn seal(tool: &Path, binary: &Path, output: &Path) {
+    let out = Command::new(tool)
+        .arg(binary)
+        .args(["-s", ".text", "-s", ".rodata", "-s", "debugoff_*", "-o"])
+        .arg(output)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+}
+
+#[test]
+fn sealed_example() {
+    let binary = build_examples(true).join("simple_integrity");
+    let tool = build_seal();
+    let sealed = binary.with_file_name("simple_integrity_sealed");
+    let patched = binary.with_file_name("simple_integrity_patched");
+
+    // Not sealed: terminated by the default response policy, `exit_group(0)`.
+    let out = run(&binary, Tracer::None);
+    assert_eq!(0, out.status, "{:?}", out);
+    assert!(!out.stdout.contains("Example complete"), "{:?}", out);
+
+    seal(&tool, &binary, &sealed);
+    let out = run(&sealed, Tracer::None);
+    assert_eq!(0, out.status, "{:?}", out);
+    assert!(out.stdout.contains("Example complete!"), "{:?}", out);
+
+    // Sealing again gives the same binary.
+    seal(&tool, &sealed, &patched);
+    assert_eq!(fs::read(&sealed).unwrap(), fs::read(&patched).unwrap());
+
+    // Patch the message printed at the end (in `.rodata`).
+    let mut data = fs::read(&sealed).unwrap();
+    let pos = data.windows(DONE.len()).position(|w| w == DONE).unwrap();
+    data[pos + DONE.len() - 1] = b'?';
+    fs::write(&patched, &data).unwrap();
+
+    let out = run(&patched, Tracer::None);
+    assert_eq!(0, out.status, "{:?}", out);
+    assert!(!out.stdout.contains("Example complete"), "{:?}", out);
+}
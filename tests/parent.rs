@@ -0,0 +1,51 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Run an example checking its parent from an allowed and from a denied parent, and look for the
+// denied names in the binary.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::build_examples;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::process::Command;
+
+// Names that must not be stored in plain text in the binary.
+const NAMES: &[&str] = &["lldb-server", "valgrind", "gdbserver", "debugoff-denied"];
+
+#[test]
+fn release_profile() {
+    let dir = build_examples(true);
+    let binary = dir.join("simple_parent");
+
+    let data = fs::read(&binary).unwrap();
+    for name in NAMES {
+        let found = data.windows(name.len()).any(|w| w == name.as_bytes());
+        assert!(!found, "`{}` found in the binary", name);
+    }
+
+    let out = Command::new(&binary).output().unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Example complete!"));
+
+    // Launch the example from a shell named after a denied program (the name registered by the
+    // example with `deny_parent!`).
+    let shell = dir.join("debugoff-denied");
+    let _ = fs::remove_file(&shell);
+    symlink("/bin/sh", &shell).unwrap();
+    let out = Command::new(&shell)
+        .arg("-c")
+        .arg(format!("{}; echo exited", binary.display()))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("exited"), "{:?}", out);
+    assert!(!stdout.contains("Example complete!"), "{:?}", out);
+}
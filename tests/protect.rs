@@ -0,0 +1,62 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// The checks are compiled out in debug builds: these tests verify that the annotated functions
+// keep their behavior with every kind of signature and return path.
+
+#![cfg(feature = "macros")]
+
+#[debugoff::protect]
+fn default_args(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[debugoff::protect(checks = "ptrace,multi_ptrace,tracerpid", placement = "entry,exit")]
+fn early_return(v: &str) -> Result<u32, std::num::ParseIntError> {
+    if v.is_empty() {
+        return Ok(0);
+    }
+    let n: u32 = v.parse()?;
+    Ok(n * 2)
+}
+
+#[debugoff::protect(checks = "tracerpid,timing", placement = "random")]
+fn generic<T: Clone>(v: &T) -> T {
+    v.clone()
+}
+
+#[debugoff::critical]
+fn critical(v: &[u32]) -> u32 {
+    v.iter().sum()
+}
+
+struct Counter(u32);
+
+impl Counter {
+    #[debugoff::protect(placement = "exit")]
+    fn incr(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+#[test]
+fn protect() {
+    assert_eq!(3, default_args(1, 2));
+
+    assert_eq!(Ok(0), early_return(""));
+    assert_eq!(Ok(42), early_return("21"));
+    assert!(early_return("x").is_err());
+
+    assert_eq!(String::from("a"), generic(&String::from("a")));
+
+    assert_eq!(6, critical(&[1, 2, 3]));
+
+    let mut c = Counter(0);
+    assert_eq!(1, c.incr());
+    assert_eq!(2, c.incr());
+}
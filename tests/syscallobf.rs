@@ -0,0 +1,208 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Build an example in release mode, disassemble it with objdump and look for the syscall numbers
+// used by debugoff being loaded as literals right before a trap instruction.
+//
+// With `syscallobf` the numbers must not be found. Without it, they must be found (which also
+// proves that the scan works). The example is built with every combination of the optional
+// features.
+//
+// The macros drawing the shape of the `ptrace` chains at compile time are also checked: every
+// call must be a separate trap instruction, so the number of traps scales with the number of
+// chains.
+//
+// The tests fail when objdump is not installed, unless `DEBUGOFF_SKIP_OBJDUMP` is set.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{build_examples_with, Features, FEATURE_SETS};
+use libc::c_long;
+use std::env;
+use std::process::Command;
+
+// Trap instruction and registers holding the syscall number, as printed by objdump.
+#[cfg(target_arch = "x86_64")]
+const TRAP: &str = "syscall";
+#[cfg(target_arch = "x86_64")]
+const NR_REGS: &[&str] = &["%eax", "%rax"];
+
+#[cfg(target_arch = "x86")]
+const TRAP: &str = "int";
+#[cfg(target_arch = "x86")]
+const NR_REGS: &[&str] = &["%eax"];
+
+#[cfg(target_arch = "arm")]
+const TRAP: &str = "svc";
+#[cfg(target_arch = "arm")]
+const NR_REGS: &[&str] = &["r7", "r8"];
+
+#[cfg(target_arch = "aarch64")]
+const TRAP: &str = "svc";
+#[cfg(target_arch = "aarch64")]
+const NR_REGS: &[&str] = &["x8", "w8"];
+
+#[cfg(target_arch = "riscv64")]
+const TRAP: &str = "ecall";
+#[cfg(target_arch = "riscv64")]
+const NR_REGS: &[&str] = &["a7"];
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+const TRAP: &str = "syscall";
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+const NR_REGS: &[&str] = &["v0"];
+
+// Instructions loading an immediate value into a register.
+const LOADS: &[&str] = &["mov", "movl", "movq", "movz", "movw", "li"];
+
+// Number of instructions preceding a trap where the syscall number is looked for.
+const WINDOW: usize = 8;
+
+// Environment variable which allows the test to be skipped when objdump is not installed.
+const SKIP_VAR: &str = "DEBUGOFF_SKIP_OBJDUMP";
+
+fn disassemble(name: &str, features: Features) -> Option<String> {
+    let binary = build_examples_with(true, features).join(name);
+    match Command::new("objdump").arg("-d").arg(&binary).output() {
+        Ok(out) if out.status.success() => Some(String::from_utf8_lossy(&out.stdout).into_owned()),
+        _ => None,
+    }
+}
+
+// Functions placed in their own sections, so that they can be disassembled separately.
+#[inline(never)]
+#[link_section = "debugoff_shape_2"]
+fn shape_2() -> bool {
+    debugoff::multi_ptraceme_checked!(outer = 2..3, inner = 1..2).is_ok()
+}
+
+#[inline(never)]
+#[link_section = "debugoff_shape_8"]
+fn shape_8() -> bool {
+    debugoff::multi_ptraceme_checked!(outer = 8..9, inner = 1..2).is_ok()
+}
+
+/// Disassemble `section` of the test binary.
+fn disassemble_section(section: &str) -> Option<String> {
+    let binary = env::current_exe().unwrap();
+    match Command::new("objdump")
+        .args(["-d", "-j", section])
+        .arg(&binary)
+        .output()
+    {
+        Ok(out) if out.status.success() => Some(String::from_utf8_lossy(&out.stdout).into_owned()),
+        _ => None,
+    }
+}
+
+fn skip_without_objdump() {
+    assert!(
+        env::var_os(SKIP_VAR).is_some(),
+        "objdump not available (set {} to skip the test)",
+        SKIP_VAR
+    );
+    eprintln!("objdump not available, skipped because {} is set", SKIP_VAR);
+}
+
+fn parse_imm(op: &str) -> Option<c_long> {
+    let op = op.trim_start_matches(['$', '#']);
+    match op.strip_prefix("0x") {
+        Some(hex) => c_long::from_str_radix(hex, 16).ok(),
+        None => op.parse().ok(),
+    }
+}
+
+/// Return true if `ins` loads one of `numbers` into a syscall number register.
+fn loads_number(ins: &str, numbers: &[c_long]) -> bool {
+    let mut parts = ins.split_whitespace();
+    let mnemonic = parts.next().unwrap_or("");
+    if !LOADS.contains(&mnemonic) {
+        return false;
+    }
+
+    let operands: Vec<&str> = parts.flat_map(|p| p.split(',')).collect();
+    operands.iter().any(|op| NR_REGS.contains(op))
+        && operands
+            .iter()
+            .filter_map(|op| parse_imm(op))
+            .any(|imm| numbers.contains(&imm))
+}
+
+/// Count the traps preceded by a literal load of one of `numbers`, and the total number of traps.
+fn scan(disasm: &str, numbers: &[c_long]) -> (usize, usize) {
+    // Keep only the instruction text (the last tab-separated field of each line).
+    let instructions: Vec<&str> = disasm
+        .lines()
+        .filter(|l| l.starts_with(' ') && l.matches('\t').count() >= 2)
+        .filter_map(|l| l.rsplit('\t').next())
+        .map(|i| i.trim())
+        .collect();
+
+    let mut found = 0;
+    let mut traps = 0;
+    for (i, ins) in instructions.iter().enumerate() {
+        if ins.split_whitespace().next() != Some(TRAP) {
+            continue;
+        }
+        traps += 1;
+        if instructions[i.saturating_sub(WINDOW)..i]
+            .iter()
+            .any(|prev| loads_number(prev, numbers))
+        {
+            found += 1;
+        }
+    }
+
+    (found, traps)
+}
+
+#[test]
+fn syscall_numbers_hidden() {
+    for features in FEATURE_SETS {
+        let disasm = match disassemble("simple1", features) {
+            Some(d) => d,
+            None => return skip_without_objdump(),
+        };
+
+        let numbers = [libc::SYS_ptrace, libc::SYS_getpid];
+        let (found, traps) = scan(&disasm, &numbers);
+        assert!(traps > 0, "{:?}", features);
+
+        if features.syscallobf {
+            assert_eq!(0, found, "{:?}", features);
+        } else {
+            assert!(found > 0, "{:?}", features);
+        }
+    }
+}
+
+#[test]
+fn multi_ptraceme_sites() {
+    // Keep the functions in the binary (they are not called: `PTRACE_TRACEME` would make the test
+    // runner the tracer of the tests).
+    std::hint::black_box([shape_2 as fn() -> bool, shape_8]);
+
+    let (small, large) = match (
+        disassemble_section("debugoff_shape_2"),
+        disassemble_section("debugoff_shape_8"),
+    ) {
+        (Some(small), Some(large)) => (small, large),
+        _ => return skip_without_objdump(),
+    };
+
+    let (_, small) = scan(&small, &[]);
+    let (_, large) = scan(&large, &[]);
+    assert!(small >= 2, "{} traps", small);
+    assert!(
+        large >= 3 * small,
+        "{} traps, {} with 2 chains",
+        large,
+        small
+    );
+}
@@ -0,0 +1,84 @@
+// DebugOff
+// Copyright (C) 2022 0xor0ne
+//
+// Licensed under:
+// - GPL-3.0 when "obfuscate" feature is enabled;
+// - MIT when "obfuscate" feature IS NOT enabled;
+
+// Run the examples with and without a tracer, built with every combination of the optional
+// features. The checks are enabled only in release builds: in release the traced examples must
+// terminate before completing, in debug they must complete as if no check was present.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{build_examples_with, run, Features, Tracer, FEATURE_SETS};
+
+// Examples and the line printed when they complete.
+const EXAMPLES: &[(&str, &str)] = &[
+    ("simple1", "Example complete!"),
+    ("simple1_multi_ptrace", "Example complete!"),
+    ("simple2", "Example complete!"),
+    ("simple2_multi_ptrace", "Example complete!"),
+    ("simple_threads", "Time in main thread"),
+    ("simple_threads_multi_ptrace", "Time in main thread"),
+    ("simple_tracerpid", "Example complete!"),
+    ("simple_protect", "Example complete!"),
+    ("simple_critical", "Example complete!"),
+    ("simple_sigtrap", "Example complete!"),
+    ("simple_guardian", "Example complete!"),
+    ("simple_multi_shape", "Example complete!"),
+    ("simple_protect_here", "Example complete!"),
+];
+
+fn check_examples(release: bool, features: Features) {
+    let dir = build_examples_with(release, features);
+
+    for (name, done) in EXAMPLES {
+        let binary = dir.join(name);
+        let name = format!("{} {:?}", name, features);
+
+        let out = run(&binary, Tracer::None);
+        assert_eq!(0, out.status, "{}: {:?}", name, out);
+        assert!(out.stdout.contains(done), "{}: {:?}", name, out);
+
+        for tracer in [Tracer::TraceMe, Tracer::Attach] {
+            let out = run(&binary, tracer);
+            if release {
+                // Terminated by the default response policy, `exit_group(0)`.
+                assert_eq!(0, out.status, "{} {:?}: {:?}", name, tracer, out);
+                assert!(
+                    !out.stdout.contains(done),
+                    "{} {:?}: {:?}",
+                    name,
+                    tracer,
+                    out
+                );
+            } else {
+                assert_eq!(0, out.status, "{} {:?}: {:?}", name, tracer, out);
+                assert!(
+                    out.stdout.contains(done),
+                    "{} {:?}: {:?}",
+                    name,
+                    tracer,
+                    out
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn debug_profile() {
+    for features in FEATURE_SETS {
+        check_examples(false, features);
+    }
+}
+
+#[test]
+fn release_profile() {
+    for features in FEATURE_SETS {
+        check_examples(true, features);
+    }
+}